@@ -26,6 +26,25 @@ mod imp {
                 });)*
                 group.finish();
             }
+            {
+                let mut group = $c.benchmark_group("create_pooled_channel");
+                $(group.bench_function(stringify!($type), |b| {
+                    let pool = oneshot::ChannelPool::<$type>::new();
+                    b.iter(|| pool.channel())
+                });)*
+                group.finish();
+            }
+            {
+                let mut group = $c.benchmark_group("create_pooled_and_send");
+                $(group.bench_function(stringify!($type), |b| {
+                    let pool = oneshot::ChannelPool::new();
+                    b.iter(|| {
+                        let (sender, _receiver) = pool.channel();
+                        sender.send(criterion::black_box($value)).unwrap()
+                    });
+                });)*
+                group.finish();
+            }
             {
                 let mut group = $c.benchmark_group("create_and_send");
                 $(group.bench_function(stringify!($type), |b| {
@@ -83,10 +102,13 @@ mod imp {
         );
 
         bench_try_recv(c);
+        bench_scatter(c);
         #[cfg(feature = "std")]
         bench_recv_deadline_now(c);
         #[cfg(feature = "std")]
         bench_recv_timeout_zero(c);
+        #[cfg(feature = "std")]
+        bench_round_trip(c);
     }
 
     fn bench_try_recv(c: &mut criterion::Criterion) {
@@ -100,6 +122,50 @@ mod imp {
         });
     }
 
+    /// Creates 16 channels, sends on all of them and receives all the messages.
+    fn bench_scatter(c: &mut criterion::Criterion) {
+        const N: usize = 16;
+        let mut group = c.benchmark_group("scatter_16");
+        group.bench_function("channel", |b| {
+            b.iter(|| {
+                let (senders, receivers): (Vec<_>, Vec<_>) =
+                    (0..N).map(|_| oneshot::channel::<u128>()).unzip();
+                for sender in senders {
+                    sender.send(criterion::black_box(1)).unwrap();
+                }
+                receivers
+                    .iter()
+                    .map(|r| r.try_recv().unwrap())
+                    .sum::<u128>()
+            });
+        });
+        group.bench_function("channels", |b| {
+            b.iter(|| {
+                let (senders, receivers) = oneshot::channels::<u128>(N);
+                for sender in senders {
+                    sender.send(criterion::black_box(1)).unwrap();
+                }
+                receivers
+                    .iter()
+                    .map(|r| r.try_recv().unwrap())
+                    .sum::<u128>()
+            });
+        });
+        group.bench_function("channels_array", |b| {
+            b.iter(|| {
+                let (senders, receivers) = oneshot::channels_array::<u128, N>();
+                for sender in senders {
+                    sender.send(criterion::black_box(1)).unwrap();
+                }
+                receivers
+                    .iter()
+                    .map(|r| r.try_recv().unwrap())
+                    .sum::<u128>()
+            });
+        });
+        group.finish();
+    }
+
     #[cfg(feature = "std")]
     fn bench_recv_deadline_now(c: &mut criterion::Criterion) {
         let now = std::time::Instant::now();
@@ -135,4 +201,65 @@ mod imp {
             });
         }
     }
+
+    /// Measures the latency of sending a message to another thread and receiving the reply, with
+    /// both threads receiving with `Receiver::recv` or `Receiver::recv_ref`, and with their
+    /// `_with` siblings and each strategy. The plain methods wait on a futex with the `futex`
+    /// feature, so compare with and without `--features futex`.
+    #[cfg(feature = "std")]
+    fn bench_round_trip(c: &mut criterion::Criterion) {
+        use oneshot::parker::WaitStrategy;
+        use std::time::{Duration, Instant};
+
+        fn round_trips(
+            iters: u64,
+            by_ref: bool,
+            strategy: Option<&'static WaitStrategy>,
+        ) -> Duration {
+            let recv = move |receiver: oneshot::Receiver<u128>| match (by_ref, strategy) {
+                (true, None) => receiver.recv_ref().unwrap(),
+                (false, None) => receiver.recv().unwrap(),
+                (true, Some(strategy)) => receiver.recv_ref_with(strategy).unwrap(),
+                (false, Some(strategy)) => receiver.recv_with(strategy).unwrap(),
+            };
+            let (requests, request_receivers): (Vec<_>, Vec<_>) =
+                (0..iters).map(|_| oneshot::channel::<u128>()).unzip();
+            let (response_senders, responses): (Vec<_>, Vec<_>) =
+                (0..iters).map(|_| oneshot::channel::<u128>()).unzip();
+
+            let echo = std::thread::spawn(move || {
+                for (request, response) in request_receivers.into_iter().zip(response_senders) {
+                    response.send(recv(request)).unwrap();
+                }
+            });
+            let start = Instant::now();
+            for (request, response) in requests.into_iter().zip(responses) {
+                request.send(criterion::black_box(1234567)).unwrap();
+                recv(response);
+            }
+            let elapsed = start.elapsed();
+            echo.join().unwrap();
+            elapsed
+        }
+
+        static NO_SPIN: WaitStrategy = WaitStrategy::no_spin();
+        static SPIN_THEN_PARK: WaitStrategy = WaitStrategy::spin_then_park(10_000);
+        static ADAPTIVE: WaitStrategy = WaitStrategy::adaptive(10_000);
+        let strategies: [(&str, Option<&'static WaitStrategy>); 4] = [
+            ("default", None),
+            ("no_spin", Some(&NO_SPIN)),
+            ("spin_then_park", Some(&SPIN_THEN_PARK)),
+            ("adaptive", Some(&ADAPTIVE)),
+        ];
+
+        for (group_name, by_ref) in [("round_trip_recv", false), ("round_trip_recv_ref", true)] {
+            let mut group = c.benchmark_group(group_name);
+            for (name, strategy) in strategies {
+                group.bench_function(name, |b| {
+                    b.iter_custom(|iters| round_trips(iters, by_ref, strategy))
+                });
+            }
+            group.finish();
+        }
+    }
 }
@@ -0,0 +1,108 @@
+use core::alloc::Layout;
+use core::fmt;
+use core::ptr::NonNull;
+
+// Under loom, `Global` allocates through `loom::alloc`, so loom detects leaked channels. That is
+// the substitution the `loombox::Box` used to provide, done at the allocator level, where every
+// allocator type goes through it. `loombox::Box` can't take its place, since channel allocations
+// are made from a `Layout` rather than a typed value.
+#[cfg(not(oneshot_loom))]
+use alloc::alloc::{alloc, dealloc};
+#[cfg(oneshot_loom)]
+use loom::alloc::{alloc, dealloc};
+
+/// An allocator that can hold the shared state of a channel created with
+/// [`channel_in`](crate::channel_in).
+///
+/// This is a minimal, stable stand-in for the unstable `core::alloc::Allocator` trait. On nightly,
+/// allocators implementing that trait can be used by wrapping them in `AllocatorApi`, which is
+/// available when compiling with `RUSTFLAGS="--cfg oneshot_allocator_api"`.
+///
+/// The allocator is moved into the channel allocation itself, so the channel endpoints stay the
+/// size of a single pointer. Whichever endpoint frees the channel moves the allocator back out
+/// and uses it to deallocate the memory.
+///
+/// # Safety
+///
+/// Memory returned from [`Allocator::allocate`] must be valid for reads and writes of
+/// `layout.size()` bytes, be aligned to `layout.align()` and stay valid until it is passed to
+/// [`Allocator::deallocate`]. The allocator can be moved between the two calls, and the calls
+/// can happen on different threads.
+pub unsafe trait Allocator {
+    /// Allocates memory fitting `layout`.
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError>;
+
+    /// Deallocates memory previously returned from [`Allocator::allocate`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned from a call to `allocate` on this allocator, and `layout`
+    /// must be the same layout that was passed to that call.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+}
+
+/// The global memory allocator. Used by [`channel`](crate::channel).
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        // SAFETY: a channel allocation is never zero sized. It always holds the channel state.
+        NonNull::new(unsafe { alloc(layout) }).ok_or(AllocError)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        dealloc(ptr.as_ptr(), layout)
+    }
+}
+
+/// Uses an allocator implementing the unstable `core::alloc::Allocator` trait for a channel.
+///
+/// Only available when compiling with `RUSTFLAGS="--cfg oneshot_allocator_api"` on a nightly
+/// compiler.
+#[cfg(oneshot_allocator_api)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AllocatorApi<A>(pub A);
+
+#[cfg(oneshot_allocator_api)]
+unsafe impl<A: core::alloc::Allocator> Allocator for AllocatorApi<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        match self.0.allocate(layout) {
+            Ok(ptr) => Ok(ptr.cast()),
+            Err(_) => Err(AllocError),
+        }
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.0.deallocate(ptr, layout)
+    }
+}
+
+/// An error returned from [`Allocator::allocate`] when the allocator fails to allocate memory.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "memory allocation failed".fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
@@ -0,0 +1,177 @@
+use crate::{channel_at, AllocError, Allocation, Allocator, Global, Receiver, Sender};
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::fmt;
+use core::ptr::{self, NonNull};
+
+#[cfg(not(oneshot_loom))]
+use core::sync::atomic::{AtomicUsize, Ordering::*};
+#[cfg(oneshot_loom)]
+use loom::sync::atomic::{AtomicUsize, Ordering::*};
+
+/// Creates `n` oneshot channels in a single allocation, and returns their endpoints. The sender
+/// and receiver at the same index belong to the same channel.
+///
+/// Works like calling [`channel`](crate::channel) `n` times, but allocates only once. Every
+/// endpoint can be moved and dropped on its own, and the memory is freed once all endpoints of
+/// all the channels are gone.
+///
+/// Calls [`handle_alloc_error`](alloc::alloc::handle_alloc_error) if the allocation fails.
+///
+/// ```rust
+/// # #[cfg(all(feature = "std", not(feature = "loom")))] {
+/// let (senders, receivers) = oneshot::channels::<usize>(4);
+/// for (shard, sender) in senders.into_iter().enumerate() {
+///     std::thread::spawn(move || sender.send(shard * 10));
+/// }
+/// let replies: Vec<_> = receivers.into_iter().map(|r| r.recv().unwrap()).collect();
+/// assert_eq!(replies, [0, 10, 20, 30]);
+/// # }
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn channels<T>(
+    n: usize,
+) -> (
+    Vec<Sender<T, BatchAllocator>>,
+    Vec<Receiver<T, BatchAllocator>>,
+) {
+    let mut senders = Vec::with_capacity(n);
+    let mut receivers = Vec::with_capacity(n);
+    if let Some(batch) = Batch::<T>::new(n) {
+        for i in 0..n {
+            // SAFETY: every index below `n` is used exactly once.
+            let (sender, receiver) = unsafe { batch.channel(i) };
+            senders.push(sender);
+            receivers.push(receiver);
+        }
+    }
+    (senders, receivers)
+}
+
+/// Like [`channels`], but creates `N` channels and returns their endpoints in arrays.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// let ([sender_a, sender_b], [receiver_a, receiver_b]) = oneshot::channels_array::<&str, 2>();
+/// sender_b.send("b").unwrap();
+/// drop(sender_a);
+/// assert_eq!(receiver_b.try_recv(), Ok("b"));
+/// assert!(receiver_a.try_recv().is_err());
+/// # }
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn channels_array<T, const N: usize>() -> (
+    [Sender<T, BatchAllocator>; N],
+    [Receiver<T, BatchAllocator>; N],
+) {
+    let batch = Batch::<T>::new(N);
+    let mut receivers = [(); N].map(|()| None);
+    let mut i = 0;
+    let senders = [(); N].map(|()| {
+        // SAFETY: `N` is not zero since we are here, so the batch exists. Every index below `N` is
+        // used exactly once.
+        let (sender, receiver) = unsafe { batch.as_ref().unwrap().channel(i) };
+        receivers[i] = Some(receiver);
+        i += 1;
+        sender
+    });
+    (senders, receivers.map(Option::unwrap))
+}
+
+/// The [`Allocator`] of channels created by [`channels`] and [`channels_array`]. Frees the
+/// shared allocation once the last of its channels is freed.
+pub struct BatchAllocator {
+    header: NonNull<Header>,
+}
+
+// SAFETY: the header is thread safe.
+unsafe impl Send for BatchAllocator {}
+unsafe impl Sync for BatchAllocator {}
+
+unsafe impl Allocator for BatchAllocator {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        // SAFETY: the header is alive as long as any channel in the batch is.
+        let header = self.header.as_ref();
+        // ORDERING: we use acquire-release ordering so whoever frees the batch synchronizes with
+        // the accesses to all other channels in it.
+        if header.channels.fetch_sub(1, AcqRel) == 1 {
+            let layout = header.layout;
+            ptr::drop_in_place(self.header.as_ptr());
+            Global.deallocate(self.header.cast(), layout);
+        }
+    }
+}
+
+impl fmt::Debug for BatchAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchAllocator").finish_non_exhaustive()
+    }
+}
+
+/// The start of a batch allocation. Followed by the channel allocations.
+struct Header {
+    /// The number of channels in the batch that have not been freed yet.
+    channels: AtomicUsize,
+    /// The layout of the whole batch.
+    layout: Layout,
+}
+
+/// A freshly allocated batch, whose channels have not been created yet.
+struct Batch<T> {
+    header: NonNull<Header>,
+    channels: NonNull<Allocation<T, BatchAllocator>>,
+}
+
+impl<T> Batch<T> {
+    /// Allocates a batch of `n` channels. Returns `None` if `n` is zero.
+    fn new(n: usize) -> Option<Self> {
+        if n == 0 {
+            return None;
+        }
+        let (layout, offset) = Layout::new::<Header>()
+            .extend(Layout::array::<Allocation<T, BatchAllocator>>(n).expect("capacity overflow"))
+            .expect("capacity overflow");
+        let layout = layout.pad_to_align();
+        let header = match Global.allocate(layout) {
+            Ok(ptr) => ptr,
+            Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+        };
+        // SAFETY: the memory was just allocated, and starts with room for the header.
+        unsafe {
+            header.cast::<Header>().as_ptr().write(Header {
+                channels: AtomicUsize::new(n),
+                layout,
+            })
+        };
+        Some(Self {
+            header: header.cast(),
+            // SAFETY: the channel allocations start `offset` bytes into the batch.
+            channels: unsafe { NonNull::new_unchecked(header.as_ptr().add(offset)).cast() },
+        })
+    }
+
+    /// Creates the channel at `index`.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be lower than the number of channels in the batch, and must only be used
+    /// once.
+    unsafe fn channel(
+        &self,
+        index: usize,
+    ) -> (Sender<T, BatchAllocator>, Receiver<T, BatchAllocator>) {
+        let allocation = NonNull::new_unchecked(self.channels.as_ptr().add(index));
+        let alloc = BatchAllocator {
+            header: self.header,
+        };
+        // SAFETY: the allocation is unused and has the layout of a channel sending `T`. The batch
+        // allocator frees the whole batch once every channel in it has been freed.
+        channel_at(allocation.cast(), alloc)
+    }
+}
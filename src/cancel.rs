@@ -0,0 +1,225 @@
+use crate::parker::{Park, ParkDeadline};
+use crate::{
+    Allocator, Receiver, ReceiverWaker, RecvCancelledError, RecvCancelledTimeoutError,
+    RecvTimeoutError,
+};
+use core::fmt;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+#[cfg(oneshot_loom)]
+use loom::sync::{
+    atomic::{AtomicBool, Ordering::*},
+    Arc, Mutex, MutexGuard,
+};
+#[cfg(not(oneshot_loom))]
+use std::sync::{
+    atomic::{AtomicBool, Ordering::*},
+    Arc, Mutex, MutexGuard,
+};
+
+/// Interrupts blocking receives started with [`Receiver::recv_cancellable`] and its siblings.
+///
+/// Cancelling the token wakes up every thread blocked on it, and makes all later receives with
+/// it return right away unless the message is already there. Clones of the token share the same
+/// cancellation.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// use oneshot::{CancelToken, RecvCancelledError};
+///
+/// let token = CancelToken::new();
+/// let (_sender, receiver) = oneshot::channel::<u32>();
+///
+/// let worker = {
+///     let token = token.clone();
+///     std::thread::spawn(move || receiver.recv_cancellable(&token))
+/// };
+/// token.cancel();
+/// assert_eq!(worker.join().unwrap(), Err(RecvCancelledError::Cancelled));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    cancelled: AtomicBool,
+    threads: Mutex<ThreadList>,
+}
+
+struct ThreadList {
+    /// Each blocked thread with its key.
+    threads: Vec<(usize, crate::thread::Thread)>,
+    next_key: usize,
+}
+
+impl CancelToken {
+    /// Creates a new token that is not cancelled.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                threads: Mutex::new(ThreadList {
+                    threads: Vec::new(),
+                    next_key: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Cancels the token, waking up all threads blocked in a receive with it. Their receivers stay
+    /// usable.
+    pub fn cancel(&self) {
+        // ORDERING: the lock below orders the flag with the registration of blocked threads.
+        self.inner.cancelled.store(true, Relaxed);
+        let threads: Vec<_> = self.lock().threads.iter().map(|(_, t)| t.clone()).collect();
+        threads.iter().for_each(crate::thread::Thread::unpark);
+    }
+
+    /// Returns true if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        // ORDERING: see `cancel`.
+        self.inner.cancelled.load(Relaxed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ThreadList> {
+        // A panic while holding the lock can not leave the list inconsistent.
+        self.inner.threads.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Registers the current thread to be unparked by `cancel`, until the returned guard is
+    /// dropped.
+    fn register(&self) -> Registration<'_> {
+        let mut list = self.lock();
+        let key = list.next_key;
+        list.next_key += 1;
+        list.threads.push((key, crate::thread::current()));
+        Registration { token: self, key }
+    }
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A thread registered with a [`CancelToken`].
+struct Registration<'a> {
+    token: &'a CancelToken,
+    key: usize,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.token.lock().threads.retain(|(k, _)| *k != self.key);
+    }
+}
+
+/// Parks the current thread like the standard library parker, but gives up waiting once the
+/// token is cancelled. The token unparks the thread when cancelled.
+struct CancelPark<'a> {
+    token: &'a CancelToken,
+}
+
+impl Park for CancelPark<'_> {
+    fn receiver_waker(&self) -> Option<ReceiverWaker> {
+        Some(ReceiverWaker::current_thread())
+    }
+
+    fn park(&self) {
+        crate::thread::park()
+    }
+}
+
+impl ParkDeadline for CancelPark<'_> {
+    /// No deadline at all if `None`.
+    type Instant = Option<Instant>;
+
+    fn park_until(&self, deadline: &Self::Instant) -> bool {
+        if self.token.is_cancelled() {
+            return false;
+        }
+        match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) => crate::thread::park_timeout(timeout),
+                None => return false,
+            },
+            None => crate::thread::park(),
+        }
+        true
+    }
+}
+
+impl<T, A: Allocator> Receiver<T, A> {
+    /// Like [`Receiver::recv_ref`], but gives up waiting when `token` is
+    /// [cancelled](CancelToken::cancel). Returns:
+    ///  * `Ok(message)` if the message arrived before the token was cancelled.
+    ///  * `Err(Cancelled)` if the token was cancelled first. The receiver stays usable, like after
+    ///    a timeout in [`Receiver::recv_timeout`].
+    ///  * `Err(Disconnected)` if the sender was dropped before sending anything, or if the message
+    ///    has already been extracted by a previous receive call.
+    ///
+    /// If the token is already cancelled, this only checks for a message without blocking.
+    pub fn recv_cancellable(&self, token: &CancelToken) -> Result<T, RecvCancelledError> {
+        self.recv_cancellable_until(None, token)
+            .map_err(|error| match error {
+                RecvCancelledTimeoutError::Cancelled | RecvCancelledTimeoutError::Timeout => {
+                    RecvCancelledError::Cancelled
+                }
+                RecvCancelledTimeoutError::Disconnected => RecvCancelledError::Disconnected,
+            })
+    }
+
+    /// Like [`Receiver::recv_cancellable`], but will not block longer than `timeout`. Returns
+    /// `Err(Timeout)` if neither the message arrived nor the token was cancelled before the
+    /// timeout was reached.
+    pub fn recv_cancellable_timeout(
+        &self,
+        timeout: Duration,
+        token: &CancelToken,
+    ) -> Result<T, RecvCancelledTimeoutError> {
+        self.recv_cancellable_until(Instant::now().checked_add(timeout), token)
+    }
+
+    /// Like [`Receiver::recv_cancellable`], but will not block longer than until `deadline`.
+    /// Returns `Err(Timeout)` if neither the message arrived nor the token was cancelled before
+    /// the deadline was reached.
+    pub fn recv_cancellable_deadline(
+        &self,
+        deadline: Instant,
+        token: &CancelToken,
+    ) -> Result<T, RecvCancelledTimeoutError> {
+        self.recv_cancellable_until(Some(deadline), token)
+    }
+
+    fn recv_cancellable_until(
+        &self,
+        deadline: Option<Instant>,
+        token: &CancelToken,
+    ) -> Result<T, RecvCancelledTimeoutError> {
+        // Register before the parker first checks the token, so a concurrent cancel either is
+        // seen by that check or unparks us.
+        let _registration = token.register();
+        // Cancelling is handled as reaching the deadline early. That path leaves the receiver
+        // usable.
+        match self.recv_deadline_parked(&CancelPark { token }, deadline) {
+            Ok(message) => Ok(message),
+            Err(RecvTimeoutError::Disconnected) => Err(RecvCancelledTimeoutError::Disconnected),
+            Err(RecvTimeoutError::Timeout) if token.is_cancelled() => {
+                Err(RecvCancelledTimeoutError::Cancelled)
+            }
+            Err(RecvTimeoutError::Timeout) => Err(RecvCancelledTimeoutError::Timeout),
+        }
+    }
+}
@@ -1,29 +1,76 @@
-use super::{dealloc, Channel};
+use super::{dealloc, states::*, Allocator, Channel, Global};
 use core::fmt;
+use core::marker::PhantomData;
 use core::mem;
 use core::ptr::NonNull;
 
+#[cfg(not(oneshot_loom))]
+use core::sync::atomic::Ordering::AcqRel;
+#[cfg(oneshot_loom)]
+use loom::sync::atomic::Ordering::AcqRel;
+
 /// An error returned when trying to send on a closed channel. Returned from
 /// [`Sender::send`](crate::Sender::send) if the corresponding [`Receiver`](crate::Receiver)
-/// has already been dropped.
+/// has already been dropped or [closed](crate::Receiver::close).
+#[cfg_attr(
+    all(feature = "ipc", target_os = "linux", not(oneshot_loom)),
+    doc = "Also returned from [`ipc::Sender::send`](crate::ipc::Sender::send)."
+)]
+/// Also returned from [`RaceSender::send`](crate::RaceSender::send) if another clone of the
+/// sender sent a message first.
 ///
 /// The message that could not be sent can be retreived again with [`SendError::into_inner`].
-pub struct SendError<T> {
+pub struct SendError<T, A: Allocator = Global> {
     channel_ptr: NonNull<Channel<T>>,
+    _alloc: PhantomData<A>,
 }
 
-unsafe impl<T: Send> Send for SendError<T> {}
-unsafe impl<T: Sync> Sync for SendError<T> {}
+unsafe impl<T: Send, A: Allocator + Send> Send for SendError<T, A> {}
+unsafe impl<T: Sync, A: Allocator> Sync for SendError<T, A> {}
 
 impl<T> SendError<T> {
+    /// Returns an error holding `message`, for a send that had no channel of ours to leave the
+    /// message in. The message is moved into a channel allocation of its own, which the error
+    /// frees. That keeps the error a single pointer for the sends that do have a channel.
+    pub(crate) fn unsent(message: T) -> Self {
+        // SAFETY: the new channel is only ours, and holds the message.
+        unsafe { Self::new(crate::unsent_channel(message)) }
+    }
+}
+
+impl<T, A: Allocator> SendError<T, A> {
     /// # Safety
     ///
     /// By calling this function, the caller semantically transfers ownership of the
     /// channel's resources to the created `SendError`. Thus the caller must ensure that the
     /// pointer is not used in a way which would violate this ownership transfer. Moreover,
     /// the caller must assert that the channel contains a valid, initialized message.
+    ///
+    /// The only exception is a channel in the REJECTED state. There the receiver is still alive
+    /// and shares the channel with the error, but never touches the message.
     pub(crate) const unsafe fn new(channel_ptr: NonNull<Channel<T>>) -> Self {
-        Self { channel_ptr }
+        Self {
+            channel_ptr,
+            _alloc: PhantomData,
+        }
+    }
+
+    /// Gives up the error's share of the channel after the message has been taken or dropped.
+    /// Frees the channel unless a receiver that closed the channel is still alive.
+    ///
+    /// # Safety
+    ///
+    /// Must be called exactly once, and the channel must not be accessed afterwards.
+    unsafe fn release(channel_ptr: NonNull<Channel<T>>) {
+        // ORDERING: we use acquire-release ordering so that whoever frees the channel, us or the
+        // receiver, synchronizes with the other party's accesses to the channel.
+        match channel_ptr.as_ref().state.swap(DISCONNECTED, AcqRel) {
+            // The receiver closed the channel and is still alive. It frees the channel when it
+            // observes the DISCONNECTED state.
+            REJECTED => (),
+            // We have exclusive ownership of the channel.
+            _ => dealloc::<T, A>(channel_ptr),
+        }
     }
 
     /// Consumes the error and returns the message that failed to be sent.
@@ -41,8 +88,8 @@ impl<T> SendError<T> {
         // `new`
         let message = unsafe { channel.take_message() };
 
-        // SAFETY: we own the channel
-        unsafe { dealloc(channel_ptr) };
+        // SAFETY: we took the message and do not touch the channel after this
+        unsafe { Self::release(channel_ptr) };
 
         message
     }
@@ -54,41 +101,68 @@ impl<T> SendError<T> {
     }
 }
 
-impl<T> Drop for SendError<T> {
+impl<T, A: Allocator> Drop for SendError<T, A> {
     fn drop(&mut self) {
-        // SAFETY: we have ownership of the channel and require that the message is initialized
-        // upon construction
+        // SAFETY: we have ownership of the message and require that it is initialized upon
+        // construction
         unsafe {
             self.channel_ptr.as_ref().drop_message();
-            dealloc(self.channel_ptr);
+            Self::release(self.channel_ptr);
         }
     }
 }
 
-impl<T> fmt::Display for SendError<T> {
+impl<T, A: Allocator> fmt::Display for SendError<T, A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         "sending on a closed channel".fmt(f)
     }
 }
 
-impl<T> fmt::Debug for SendError<T> {
+impl<T, A: Allocator> fmt::Debug for SendError<T, A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "SendError<{}>(_)", stringify!(T))
     }
 }
 
 #[cfg(feature = "std")]
-impl<T> std::error::Error for SendError<T> {}
+impl<T, A: Allocator> std::error::Error for SendError<T, A> {}
+
+/// An error returned from [`Sender::send_with`](crate::Sender::send_with).
+pub enum SendWithError<T, F, A: Allocator = Global> {
+    /// The [`Receiver`](crate::Receiver) was dropped or [closed](crate::Receiver::close) before
+    /// the closure was called. Contains the closure, which was never called.
+    Closed(F),
+
+    /// The [`Receiver`](crate::Receiver) was dropped or closed while the closure was running.
+    /// Contains the message returned from the closure.
+    Send(SendError<T, A>),
+}
+
+impl<T, F, A: Allocator> fmt::Display for SendWithError<T, F, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "sending on a closed channel".fmt(f)
+    }
+}
+
+impl<T, F, A: Allocator> fmt::Debug for SendWithError<T, F, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendWithError::Closed(_) => "Closed(_)".fmt(f),
+            SendWithError::Send(error) => f.debug_tuple("Send").field(error).finish(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, F, A: Allocator> std::error::Error for SendWithError<T, F, A> {}
 
 /// An error returned from receiving methods that block/wait until a message is available.
 ///
 /// The receive operation can only fail if the corresponding [`Sender`](crate::Sender) was dropped
 /// before sending any message, or if a message has already been received on the channel.
-#[cfg(any(feature = "std", feature = "async"))]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct RecvError;
 
-#[cfg(any(feature = "std", feature = "async"))]
 impl fmt::Display for RecvError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         "receiving on a closed channel".fmt(f)
@@ -125,7 +199,6 @@ impl std::error::Error for TryRecvError {}
 
 /// An error returned when failing to receive a message in a method that block/wait for a message
 /// for a while, but has a timeout after which it gives up.
-#[cfg(feature = "std")]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum RecvTimeoutError {
     /// No message arrived on the channel before the timeout was reached. The channel is still open.
@@ -136,7 +209,6 @@ pub enum RecvTimeoutError {
     Disconnected,
 }
 
-#[cfg(feature = "std")]
 impl fmt::Display for RecvTimeoutError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let msg = match self {
@@ -149,3 +221,65 @@ impl fmt::Display for RecvTimeoutError {
 
 #[cfg(feature = "std")]
 impl std::error::Error for RecvTimeoutError {}
+
+/// An error returned from [`Receiver::recv_cancellable`](crate::Receiver::recv_cancellable).
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RecvCancelledError {
+    /// The [`CancelToken`](crate::CancelToken) was cancelled before a message arrived. The channel
+    /// is still open.
+    Cancelled,
+
+    /// The channel is closed. Either the sender was dropped before sending any message, or the
+    /// message has already been extracted from the receiver.
+    Disconnected,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for RecvCancelledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            RecvCancelledError::Cancelled => "receive on channel was cancelled",
+            RecvCancelledError::Disconnected => "channel is empty and sending half is closed",
+        };
+        msg.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvCancelledError {}
+
+/// An error returned from
+/// [`Receiver::recv_cancellable_timeout`](crate::Receiver::recv_cancellable_timeout) and
+/// [`Receiver::recv_cancellable_deadline`](crate::Receiver::recv_cancellable_deadline).
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RecvCancelledTimeoutError {
+    /// The [`CancelToken`](crate::CancelToken) was cancelled before a message arrived. The channel
+    /// is still open.
+    Cancelled,
+
+    /// No message arrived on the channel before the timeout was reached. The channel is still open.
+    Timeout,
+
+    /// The channel is closed. Either the sender was dropped before sending any message, or the
+    /// message has already been extracted from the receiver.
+    Disconnected,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for RecvCancelledTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            RecvCancelledTimeoutError::Cancelled => "receive on channel was cancelled",
+            RecvCancelledTimeoutError::Timeout => "timed out waiting on channel",
+            RecvCancelledTimeoutError::Disconnected => {
+                "channel is empty and sending half is closed"
+            }
+        };
+        msg.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvCancelledTimeoutError {}
@@ -0,0 +1,50 @@
+//! Thin wrappers around the Linux futex system call. Used to block on the state of a channel
+//! directly, by the `futex` feature and by the `ipc` module.
+
+use core::ptr;
+use core::sync::atomic::AtomicU32;
+use std::time::Duration;
+
+/// Blocks until woken up by `wake` on the same futex, or until `timeout` has passed, unless
+/// `futex` no longer holds `expected`. May return spuriously.
+///
+/// A `shared` futex can be woken up from other processes mapping the same memory. Otherwise
+/// only from this process, which is cheaper for the kernel.
+pub(crate) fn wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>, shared: bool) {
+    let timeout = timeout.map(|timeout| libc::timespec {
+        tv_sec: timeout.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+        tv_nsec: timeout.subsec_nanos() as _,
+    });
+    let timeout_ptr = timeout
+        .as_ref()
+        .map_or(ptr::null(), |timeout| timeout as *const libc::timespec);
+    // SAFETY: the futex is a valid atomic for the whole call, and the timeout, if any, outlives it.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex as *const AtomicU32,
+            op(libc::FUTEX_WAIT, shared),
+            expected,
+            timeout_ptr,
+        )
+    };
+}
+
+/// Wakes up one thread blocked in `wait` on `futex`.
+///
+/// # Safety
+///
+/// The futex may already have been freed. The kernel only uses the address to find waiters, so
+/// the worst outcome is a spurious wakeup of another waiter that reused the memory, which every
+/// futex user must tolerate anyway.
+pub(crate) unsafe fn wake(futex: *const AtomicU32, shared: bool) {
+    libc::syscall(libc::SYS_futex, futex, op(libc::FUTEX_WAKE, shared), 1);
+}
+
+fn op(op: libc::c_int, shared: bool) -> libc::c_int {
+    if shared {
+        op
+    } else {
+        op | libc::FUTEX_PRIVATE_FLAG
+    }
+}
@@ -0,0 +1,291 @@
+use crate::shared::ThreadWaker;
+use crate::{Allocator, Global, Receiver, RecvError};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{self, Poll, Waker};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::task::Wake;
+use std::vec::Vec;
+
+#[cfg(oneshot_loom)]
+use loom::sync::{Mutex, MutexGuard};
+#[cfg(not(oneshot_loom))]
+use std::sync::{Mutex, MutexGuard};
+
+/// A collection of receivers, each with a key, yielding their messages in the order they arrive.
+///
+/// Every receiver in the group is registered with a waker of its own, created once when the
+/// receiver is added. When a sender sends or is dropped, that waker records which receiver
+/// became ready. So only the receivers that are actually ready get polled, no matter how many
+/// receivers the group holds.
+///
+/// Wait for the next message with [`ReceiverGroup::next_message`] or
+/// [`ReceiverGroup::poll_next`] in async code, or with [`ReceiverGroup::next_blocking`] from a
+/// regular thread.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// let mut group = oneshot::ReceiverGroup::new();
+/// let mut senders = Vec::new();
+/// for id in 0..3 {
+///     let (sender, receiver) = oneshot::channel();
+///     group.insert(id, receiver);
+///     senders.push(sender);
+/// }
+///
+/// senders.remove(1).send("second").unwrap();
+/// drop(senders.remove(0));
+///
+/// assert_eq!(group.next_blocking(), Some((1, Ok("second"))));
+/// assert_eq!(group.next_blocking(), Some((0, Err(oneshot::RecvError))));
+/// assert_eq!(group.len(), 1);
+/// # }
+/// ```
+pub struct ReceiverGroup<K, T, A: Allocator = Global> {
+    slots: Vec<Slot<K, T, A>>,
+    /// Indices of the slots without a receiver.
+    free: Vec<usize>,
+    /// The number of receivers in the group.
+    len: usize,
+    ready: Arc<ReadyQueue>,
+}
+
+struct Slot<K, T, A: Allocator> {
+    /// Records this slot as ready when woken. Reused by every receiver placed in the slot.
+    waker: Waker,
+    member: Option<Member<K, T, A>>,
+}
+
+enum Member<K, T, A: Allocator> {
+    /// The receiver is registered with the waker of its slot.
+    Waiting(K, Receiver<T, A>),
+    /// The receiver was already done when it was added.
+    Done(K, Result<T, RecvError>),
+}
+
+impl<K, T, A: Allocator> ReceiverGroup<K, T, A> {
+    /// Creates a new, empty, group.
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            ready: Arc::new(ReadyQueue {
+                state: Mutex::new(ReadyState {
+                    indices: VecDeque::new(),
+                    waker: None,
+                }),
+            }),
+        }
+    }
+
+    /// Adds `receiver` to the group. Its message is yielded together with `key`.
+    pub fn insert(&mut self, key: K, receiver: Receiver<T, A>) {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                let index = self.slots.len();
+                self.slots.push(Slot {
+                    waker: Waker::from(Arc::new(SlotWaker {
+                        index,
+                        ready: self.ready.clone(),
+                    })),
+                    member: None,
+                });
+                index
+            }
+        };
+        self.len += 1;
+
+        // Register the waker of the slot right away, so the order in which messages arrive from
+        // now on is recorded.
+        let slot = &mut self.slots[index];
+        let mut receiver = receiver;
+        let mut cx = task::Context::from_waker(&slot.waker);
+        match Pin::new(&mut receiver).poll(&mut cx) {
+            Poll::Ready(result) => {
+                slot.member = Some(Member::Done(key, result));
+                self.ready.push(index);
+            }
+            Poll::Pending => slot.member = Some(Member::Waiting(key, receiver)),
+        }
+    }
+
+    /// Returns the number of receivers in the group that have not yielded their message yet.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the group holds no receivers.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Polls for the next message, removing its receiver from the group. Returns:
+    ///  * `Poll::Ready(Some((key, Ok(message))))` when the message of a receiver has arrived.
+    ///  * `Poll::Ready(Some((key, Err(RecvError))))` when the sender of a receiver was dropped
+    ///    without sending anything.
+    ///  * `Poll::Ready(None)` when the group is empty.
+    ///  * `Poll::Pending` otherwise. The task is woken up when a receiver becomes ready.
+    pub fn poll_next(
+        &mut self,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<(K, Result<T, RecvError>)>> {
+        if self.len == 0 {
+            return Poll::Ready(None);
+        }
+        loop {
+            let index = match self.ready.pop_or_register(cx.waker()) {
+                Some(index) => index,
+                None => return Poll::Pending,
+            };
+            let slot = &mut self.slots[index];
+            let polled = match &mut slot.member {
+                Some(Member::Waiting(_, receiver)) => {
+                    let mut slot_cx = task::Context::from_waker(&slot.waker);
+                    match Pin::new(receiver).poll(&mut slot_cx) {
+                        Poll::Ready(result) => Some(result),
+                        // Woken up without the channel being done. The waker is registered again.
+                        Poll::Pending => continue,
+                    }
+                }
+                Some(Member::Done(..)) => None,
+                // The receiver already yielded its message. Its sender woke the slot up after
+                // we found the message.
+                None => continue,
+            };
+            let next = match (slot.member.take(), polled) {
+                (Some(Member::Waiting(key, _)), Some(result)) => (key, result),
+                (Some(Member::Done(key, result)), None) => (key, result),
+                _ => unreachable!(),
+            };
+            self.free.push(index);
+            self.len -= 1;
+            return Poll::Ready(Some(next));
+        }
+    }
+
+    /// Returns a future completing with the next message, see [`ReceiverGroup::poll_next`].
+    pub fn next_message(&mut self) -> NextMessage<'_, K, T, A> {
+        NextMessage { group: self }
+    }
+
+    /// Blocks the current thread until the next message arrives, and returns it, see
+    /// [`ReceiverGroup::poll_next`]. Returns `None` right away if the group is empty.
+    pub fn next_blocking(&mut self) -> Option<(K, Result<T, RecvError>)> {
+        let waker = Waker::from(Arc::new(ThreadWaker(crate::thread::current())));
+        let mut cx = task::Context::from_waker(&waker);
+        loop {
+            match self.poll_next(&mut cx) {
+                Poll::Ready(next) => break next,
+                Poll::Pending => crate::thread::park(),
+            }
+        }
+    }
+}
+
+impl<K, T, A: Allocator> Default for ReceiverGroup<K, T, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T, A: Allocator> FromIterator<(K, Receiver<T, A>)> for ReceiverGroup<K, T, A> {
+    fn from_iter<I: IntoIterator<Item = (K, Receiver<T, A>)>>(iter: I) -> Self {
+        let mut group = Self::new();
+        group.extend(iter);
+        group
+    }
+}
+
+impl<K, T, A: Allocator> Extend<(K, Receiver<T, A>)> for ReceiverGroup<K, T, A> {
+    fn extend<I: IntoIterator<Item = (K, Receiver<T, A>)>>(&mut self, iter: I) {
+        for (key, receiver) in iter {
+            self.insert(key, receiver);
+        }
+    }
+}
+
+impl<K, T, A: Allocator> fmt::Debug for ReceiverGroup<K, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReceiverGroup")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Future returned from [`ReceiverGroup::next_message`]. Completes with the next message, or
+/// with `None` if the group is empty.
+#[derive(Debug)]
+pub struct NextMessage<'a, K, T, A: Allocator = Global> {
+    group: &'a mut ReceiverGroup<K, T, A>,
+}
+
+impl<K, T, A: Allocator> Future for NextMessage<'_, K, T, A> {
+    type Output = Option<(K, Result<T, RecvError>)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.group.poll_next(cx)
+    }
+}
+
+/// The slots woken up since the group last looked, and the waker of whoever waits for the group.
+struct ReadyQueue {
+    state: Mutex<ReadyState>,
+}
+
+struct ReadyState {
+    indices: VecDeque<usize>,
+    waker: Option<Waker>,
+}
+
+impl ReadyQueue {
+    fn lock(&self) -> MutexGuard<'_, ReadyState> {
+        // A panic while holding the lock can not leave the state inconsistent.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records the slot at `index` as ready, and wakes up whoever waits for the group.
+    fn push(&self, index: usize) {
+        let mut state = self.lock();
+        state.indices.push_back(index);
+        let waker = state.waker.take();
+        drop(state);
+        // Wake outside the lock, in case the waker polls the group right away.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns the next ready slot. If there is none, stores `waker` to be woken up by the next
+    /// call to `push`.
+    fn pop_or_register(&self, waker: &Waker) -> Option<usize> {
+        let mut state = self.lock();
+        let index = state.indices.pop_front();
+        if index.is_none() {
+            match &mut state.waker {
+                Some(stored) if stored.will_wake(waker) => (),
+                stored => *stored = Some(waker.clone()),
+            }
+        }
+        index
+    }
+}
+
+/// The waker of a slot in a [`ReceiverGroup`].
+struct SlotWaker {
+    index: usize,
+    ready: Arc<ReadyQueue>,
+}
+
+impl Wake for SlotWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.push(self.index)
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.push(self.index)
+    }
+}
@@ -0,0 +1,424 @@
+//! Oneshot channels between processes on Linux.
+//!
+//! [`channel`] puts the channel state and the message in a shared memory mapping, backed by a
+//! `memfd`. Each endpoint owns a file descriptor for that memory. Move an endpoint to another
+//! process by turning it into a raw file descriptor with [`Sender::into_raw_fd`] or
+//! [`Receiver::into_raw_fd`], letting the other process inherit it, by `fork` or by spawning a
+//! child, and recreating the endpoint there with `from_raw_fd`. A blocked receiver sleeps on a
+//! futex in the shared memory, and is woken up by the sender from any process.
+//!
+//! Only `Copy` messages can be sent, since the message is copied between address spaces as raw
+//! bytes. Messages holding pointers or references are meaningless in the receiving process.
+//!
+//! If the process holding an endpoint exits without dropping it, the other endpoint is not
+//! notified. Receive with a timeout if the sending process might crash.
+//!
+//! ```rust
+//! # #[cfg(not(feature = "loom"))] {
+//! let (sender, receiver) = oneshot::ipc::channel::<u64>().unwrap();
+//! let fd = sender.into_raw_fd().unwrap();
+//!
+//! // After a `fork`, the child recreates the sender from the inherited file descriptor.
+//! let sender = unsafe { oneshot::ipc::Sender::<u64>::from_raw_fd(fd) }.unwrap();
+//! sender.send(42).unwrap();
+//!
+//! assert_eq!(receiver.recv(), Ok(42));
+//! # }
+//! ```
+
+use crate::{futex, RecvError, RecvTimeoutError, SendError, TryRecvError};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicU32, Ordering::*};
+use std::io;
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+
+// The states of the channel, with the same values as in the in-process channel. Stored in 32
+// bits, the size of a futex word. The in-process states are 32 bits too with the `futex` feature.
+#[allow(clippy::unnecessary_cast)]
+mod states {
+    pub const RECEIVING: u32 = crate::states::RECEIVING as u32;
+    pub const DISCONNECTED: u32 = crate::states::DISCONNECTED as u32;
+    pub const EMPTY: u32 = crate::states::EMPTY as u32;
+    pub const MESSAGE: u32 = crate::states::MESSAGE as u32;
+}
+use states::*;
+
+/// Creates a new oneshot channel in memory shared between processes, and returns the two
+/// endpoints. Fails if the shared memory can't be created or mapped.
+pub fn channel<T: Copy>() -> io::Result<(Sender<T>, Receiver<T>)> {
+    // SAFETY: memfd_create is called with a valid C string.
+    let fd = cvt(unsafe { libc::memfd_create(b"oneshot\0".as_ptr().cast(), libc::MFD_CLOEXEC) })?;
+    // Owns the file descriptor until it is handed to the sender.
+    let fd = Fd(fd);
+    let len = mem::size_of::<Shared<T>>() as libc::off_t;
+    // SAFETY: we own the file descriptor.
+    cvt(unsafe { libc::ftruncate(fd.0, len) })?;
+    // SAFETY: the memory was just created, and nobody else has mapped it yet.
+    let mapping = unsafe { Mapping::<T>::new(fd)? };
+    // ORDERING: the receiver's mapping is created after this, in this thread.
+    mapping.shared().state.store(EMPTY, Relaxed);
+    let receiver_fd = mapping.dup()?;
+    // SAFETY: the file descriptor refers to the memory of the channel we just created.
+    let receiver = unsafe { Mapping::new(receiver_fd)? };
+    Ok((Sender { mapping }, Receiver { mapping: receiver }))
+}
+
+/// The memory shared by both endpoints of a channel.
+#[repr(C)]
+struct Shared<T> {
+    state: AtomicU32,
+    message: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// Sending end of a channel between processes, see the [module documentation](self).
+pub struct Sender<T: Copy> {
+    mapping: Mapping<T>,
+}
+
+/// Receiving end of a channel between processes, see the [module documentation](self).
+pub struct Receiver<T: Copy> {
+    mapping: Mapping<T>,
+}
+
+// SAFETY: the endpoints only access the shared memory through atomics, and the message is only
+// accessed by one endpoint at a time, as in the in-process channel. They are not `Sync`: two
+// threads receiving through a shared `&Receiver` could both take the message.
+unsafe impl<T: Copy + Send> Send for Sender<T> {}
+unsafe impl<T: Copy + Send> Send for Receiver<T> {}
+
+impl<T: Copy> Sender<T> {
+    /// Sends `message` over the channel, waking up the receiver if it is waiting, in any process.
+    ///
+    /// Returns an error holding the message if the receiver has been dropped.
+    pub fn send(self, message: T) -> Result<(), SendError<T>> {
+        let mapping = self.into_mapping();
+        let shared = mapping.shared();
+        // SAFETY: only the sender writes the message, and the receiver only reads it after seeing
+        // the MESSAGE state.
+        unsafe { shared.message.get().write(MaybeUninit::new(message)) };
+        // ORDERING: we use release ordering to make the message visible to the receiver, and
+        // acquire ordering to see if the receiver is gone.
+        match shared.state.swap(MESSAGE, AcqRel) {
+            EMPTY => Ok(()),
+            RECEIVING => {
+                mapping.wake();
+                Ok(())
+            }
+            // The receiver is gone. The message is `Copy`, so we still have it.
+            DISCONNECTED => Err(SendError::unsent(message)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Consumes the Sender, returning the file descriptor of its shared memory. The descriptor is
+    /// inherited by child processes, both forked and spawned. Recreate the Sender with
+    /// [`Sender::from_raw_fd`], in this process or a child.
+    ///
+    /// Fails if the descriptor could not be made inheritable. The Sender is dropped then, and
+    /// the receiver sees the channel as disconnected.
+    pub fn into_raw_fd(self) -> io::Result<RawFd> {
+        self.mapping.make_inheritable()?;
+        Ok(self.into_mapping().into_raw_fd())
+    }
+
+    /// Recreates a Sender from a file descriptor returned by [`Sender::into_raw_fd`]. The
+    /// descriptor is owned by the new Sender. Fails if the shared memory can't be mapped, in
+    /// which case the descriptor is closed.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must come from [`Sender::into_raw_fd`] for a channel sending `T`, and only one Sender
+    /// may be recreated from it, across all processes. `T` must not contain any pointers or
+    /// references, since the message is copied as raw bytes into the address space of another
+    /// process, where they would dangle.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> io::Result<Self> {
+        Ok(Self {
+            mapping: Mapping::from_raw_fd(fd)?,
+        })
+    }
+
+    /// Returns true if the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        // ORDERING: only the receiver disconnects while we are alive.
+        self.mapping.shared().state.load(Relaxed) == DISCONNECTED
+    }
+
+    /// Takes the mapping out of the Sender without running its Drop implementation.
+    fn into_mapping(self) -> Mapping<T> {
+        // SAFETY: self is forgotten right away, so the mapping is not unmapped twice.
+        let mapping = unsafe { ptr::read(&self.mapping) };
+        mem::forget(self);
+        mapping
+    }
+}
+
+impl<T: Copy> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // ORDERING: nothing is published to the receiver, we only tell it we are gone.
+        if self.mapping.shared().state.swap(DISCONNECTED, Relaxed) == RECEIVING {
+            self.mapping.wake();
+        }
+    }
+}
+
+impl<T: Copy> Receiver<T> {
+    /// Checks if there is a message in the channel without blocking. Returns:
+    ///  * `Ok(message)` if there was a message in the channel.
+    ///  * `Err(Empty)` if the sender is alive but has not sent anything yet.
+    ///  * `Err(Disconnected)` if the sender was dropped before sending anything, or if the
+    ///    message has already been received.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.try_take() {
+            Some(result) => result.map_err(|RecvError| TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Blocks the current thread until the message arrives, from any process, and returns it.
+    /// Returns an error if the sender was dropped before sending anything.
+    pub fn recv(self) -> Result<T, RecvError> {
+        loop {
+            if let Some(result) = self.wait(None) {
+                break result;
+            }
+        }
+    }
+
+    /// Like [`Receiver::recv`], but will not block longer than `timeout`. The Receiver stays
+    /// usable after a timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(deadline),
+            None => loop {
+                if let Some(result) = self.wait(None) {
+                    break result.map_err(|RecvError| RecvTimeoutError::Disconnected);
+                }
+            },
+        }
+    }
+
+    /// Like [`Receiver::recv`], but will not block longer than until `deadline`. The Receiver
+    /// stays usable after a timeout.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        loop {
+            let timeout = match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if !timeout.is_zero() => timeout,
+                // The message might have arrived just in time.
+                _ => return self.try_timed_out(),
+            };
+            if let Some(result) = self.wait(Some(timeout)) {
+                break result.map_err(|RecvError| RecvTimeoutError::Disconnected);
+            }
+        }
+    }
+
+    /// Consumes the Receiver, returning the file descriptor of its shared memory. See
+    /// [`Sender::into_raw_fd`].
+    ///
+    /// Fails if the descriptor could not be made inheritable. The Receiver is dropped then, and
+    /// the sender sees the channel as disconnected.
+    pub fn into_raw_fd(self) -> io::Result<RawFd> {
+        self.mapping.make_inheritable()?;
+        // SAFETY: self is forgotten right away, so the mapping is not unmapped twice.
+        let mapping = unsafe { ptr::read(&self.mapping) };
+        mem::forget(self);
+        Ok(mapping.into_raw_fd())
+    }
+
+    /// Recreates a Receiver from a file descriptor returned by [`Receiver::into_raw_fd`]. See
+    /// [`Sender::from_raw_fd`].
+    ///
+    /// # Safety
+    ///
+    /// `fd` must come from [`Receiver::into_raw_fd`] for a channel sending `T`, and only one
+    /// Receiver may be recreated from it, across all processes. `T` must not contain any pointers
+    /// or references, see [`Sender::from_raw_fd`].
+    pub unsafe fn from_raw_fd(fd: RawFd) -> io::Result<Self> {
+        Ok(Self {
+            mapping: Mapping::from_raw_fd(fd)?,
+        })
+    }
+
+    /// Takes the message if there is one. Returns `None` if the sender has not sent anything
+    /// yet, and `Some(Err(RecvError))` if it never will.
+    fn try_take(&self) -> Option<Result<T, RecvError>> {
+        let shared = self.mapping.shared();
+        // ORDERING: we use acquire ordering to synchronize with the write of the message.
+        match shared.state.load(Acquire) {
+            MESSAGE => {
+                // ORDERING: the sender is done with the channel.
+                shared.state.store(DISCONNECTED, Relaxed);
+                // SAFETY: we are in the MESSAGE state, so the message was written.
+                Some(Ok(unsafe { (*shared.message.get()).assume_init() }))
+            }
+            DISCONNECTED => Some(Err(RecvError)),
+            EMPTY | RECEIVING => None,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The result of a receive whose deadline was reached.
+    fn try_timed_out(&self) -> Result<T, RecvTimeoutError> {
+        match self.try_take() {
+            Some(result) => result.map_err(|RecvError| RecvTimeoutError::Disconnected),
+            None => Err(RecvTimeoutError::Timeout),
+        }
+    }
+
+    /// Takes the message, or sleeps until woken up or until `timeout` has passed. Returns `None`
+    /// if the message has not arrived yet.
+    fn wait(&self, timeout: Option<Duration>) -> Option<Result<T, RecvError>> {
+        if let Some(result) = self.try_take() {
+            return Some(result);
+        }
+        let shared = self.mapping.shared();
+        // Tell the sender to wake us up. It may already be in the RECEIVING state from an
+        // earlier wait that timed out.
+        // ORDERING: the message is synchronized by `try_take`.
+        let _ = shared
+            .state
+            .compare_exchange(EMPTY, RECEIVING, Relaxed, Relaxed);
+        // The futex returns right away unless the state is still RECEIVING.
+        self.mapping.wait(RECEIVING, timeout);
+        self.try_take()
+    }
+}
+
+impl<T: Copy> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // ORDERING: the message is `Copy`, so there is nothing to synchronize when dropping it.
+        self.mapping.shared().state.swap(DISCONNECTED, Relaxed);
+    }
+}
+
+impl<T: Copy> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("fd", &self.mapping.fd.0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Copy> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("fd", &self.mapping.fd.0)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A file descriptor closed on drop.
+struct Fd(RawFd);
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        // SAFETY: we own the file descriptor.
+        unsafe { libc::close(self.0) };
+    }
+}
+
+/// The shared memory of a channel, mapped into this process, and its file descriptor. Unmapped
+/// and closed on drop.
+struct Mapping<T> {
+    ptr: NonNull<Shared<T>>,
+    fd: Fd,
+    _message: PhantomData<T>,
+}
+
+impl<T> Mapping<T> {
+    /// Maps the shared memory of a channel.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must refer to shared memory of the size of `Shared<T>`.
+    unsafe fn new(fd: Fd) -> io::Result<Self> {
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            mem::size_of::<Shared<T>>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.0,
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: NonNull::new_unchecked(ptr.cast()),
+            fd,
+            _message: PhantomData,
+        })
+    }
+
+    /// Maps the shared memory behind a file descriptor from `into_raw_fd`, marking the descriptor
+    /// as not inherited by child processes again.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must come from `into_raw_fd`, for a channel sending `T`.
+    unsafe fn from_raw_fd(fd: RawFd) -> io::Result<Self> {
+        let fd = Fd(fd);
+        cvt(libc::fcntl(fd.0, libc::F_SETFD, libc::FD_CLOEXEC))?;
+        Self::new(fd)
+    }
+
+    /// Makes the file descriptor inheritable by child processes, before `into_raw_fd`.
+    fn make_inheritable(&self) -> io::Result<()> {
+        // SAFETY: we own the file descriptor.
+        cvt(unsafe { libc::fcntl(self.fd.0, libc::F_SETFD, 0) }).map(|_| ())
+    }
+
+    /// Unmaps the memory and returns the file descriptor.
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd.0;
+        // SAFETY: we are done with the memory, and keep the file descriptor open.
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), mem::size_of::<Shared<T>>()) };
+        mem::forget(self);
+        fd
+    }
+
+    fn shared(&self) -> &Shared<T> {
+        // SAFETY: the memory stays mapped as long as we are alive.
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Returns a new file descriptor for the same memory.
+    fn dup(&self) -> io::Result<Fd> {
+        // SAFETY: we own the file descriptor.
+        cvt(unsafe { libc::fcntl(self.fd.0, libc::F_DUPFD_CLOEXEC, 0) }).map(Fd)
+    }
+
+    /// Sleeps until woken up by `wake`, or until `timeout` has passed, unless the state is no
+    /// longer `expected`. May return spuriously.
+    fn wait(&self, expected: u32, timeout: Option<Duration>) {
+        // The futex is shared, since the other endpoint may live in another process.
+        futex::wait(&self.shared().state, expected, timeout, true);
+    }
+
+    /// Wakes up the receiver sleeping in `wait`, in any process.
+    fn wake(&self) {
+        // SAFETY: the futex word is in our mapping.
+        unsafe { futex::wake(&self.shared().state, true) };
+    }
+}
+
+impl<T> Drop for Mapping<T> {
+    fn drop(&mut self) {
+        // SAFETY: the memory is not used after this. The file descriptor is closed right after.
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), mem::size_of::<Shared<T>>()) };
+    }
+}
+
+/// Turns the return value of a libc call into an error if it failed.
+fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
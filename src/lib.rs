@@ -100,19 +100,37 @@
 // * Uninitialized memory to fit the message,
 // * Uninitialized memory to fit the waker that can wake the receiving task or thread up.
 //
-// The size of the waker depends on which features are activated, it ranges from 0 to 24 bytes[1].
+// The size of the waker depends on which features are activated, it ranges from 16 to 24 bytes[1].
 // So with all features enabled each channel allocates 25 bytes plus the size of the
 // message, plus any padding needed to get correct memory alignment.
 //
+// With the `std` or `async` features the channel additionally holds a second one byte atomic and
+// a second waker. Those are used by the sender to wait for the receiver to go away, see
+// `Sender::closed` and `Sender::wait_closed`.
+//
+// The channel is allocated with the `Allocator` given to `channel_in`, the global allocator by
+// default. The allocator itself is stored right after the channel in the same allocation, so it
+// can be used to free the memory again.
+//
 // The Sender and Receiver only holds a raw pointer to the heap channel object. The last endpoint
 // to be consumed or dropped is responsible for freeing the heap memory. The first endpoint to
 // be consumed or dropped signal via the state that it is gone. And the second one see this and
 // frees the memory.
 //
+// With the `futex` feature on Linux the state is four bytes instead of one, so the receiver can
+// block on it with a futex.
+//
+// In debug builds, or with the `checked-raw` feature, the channel also starts with the type name
+// of the message and a byte of flags, used to detect misuse of `Sender::from_raw` and
+// `Receiver::from_raw`.
+//
+// With the `tracing` feature the channel also holds its id and the span it was created in, which
+// its `tracing` events are emitted in.
+//
 // ## Footnotes
 //
-// [1]: Mind that the waker only takes zero bytes when all features are disabled, making it
-//      impossible to *wait* for the message. `try_recv` is the only available method in this scenario.
+// [1]: With all features disabled the waker can only hold a type erased `parker::Unparker`,
+//      which is a pointer sized payload plus a vtable pointer. Larger unparkers are boxed.
 
 #![deny(rust_2018_idioms)]
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -121,11 +139,15 @@
 // `RUSTDOCFLAGS="--cfg docsrs" cargo +nightly doc --all-features`
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
+// Enables the nightly only allocator API for the `AllocatorApi` bridge. Build with
+// `RUSTFLAGS="--cfg oneshot_allocator_api" cargo +nightly build` to use it.
+#![cfg_attr(oneshot_allocator_api, feature(allocator_api))]
 
-#[cfg(not(oneshot_loom))]
 extern crate alloc;
 
 use core::{
+    alloc::Layout,
+    fmt,
     marker::PhantomData,
     mem::{self, MaybeUninit},
     ptr::{self, NonNull},
@@ -142,11 +164,17 @@ use loom::{
     sync::atomic::{fence, AtomicU8, Ordering::*},
 };
 
-#[cfg(all(any(feature = "std", feature = "async"), not(oneshot_loom)))]
+#[cfg(not(oneshot_loom))]
 use core::hint;
-#[cfg(all(any(feature = "std", feature = "async"), oneshot_loom))]
+#[cfg(oneshot_loom)]
 use loom::hint;
 
+// The channel state. 32 bits with the `futex` feature, the size of a futex word.
+#[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+use core::sync::atomic::AtomicU32 as AtomicState;
+#[cfg(not(all(feature = "futex", target_os = "linux", not(oneshot_loom))))]
+use AtomicU8 as AtomicState;
+
 #[cfg(feature = "async")]
 use core::{
     pin::Pin,
@@ -175,12 +203,101 @@ mod thread {
     }
 }
 
-#[cfg(oneshot_loom)]
-mod loombox;
-#[cfg(not(oneshot_loom))]
-use alloc::boxed::Box;
-#[cfg(oneshot_loom)]
-use loombox::Box;
+/// Emits a `tracing` event about a channel, given its `trace::ChannelTrace`. Does nothing without
+/// the `tracing` feature, and then does not evaluate its arguments.
+#[cfg(feature = "tracing")]
+macro_rules! channel_event {
+    ($trace:expr, $level:ident, $($fields:tt)+) => {{
+        let trace: &$crate::trace::ChannelTrace = &$trace;
+        match trace.span.id() {
+            Some(parent) => ::tracing::event!(
+                target: "oneshot",
+                parent: parent,
+                ::tracing::Level::$level,
+                channel = trace.id,
+                $($fields)+
+            ),
+            None => ::tracing::event!(
+                target: "oneshot",
+                ::tracing::Level::$level,
+                channel = trace.id,
+                $($fields)+
+            ),
+        }
+    }};
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! channel_event {
+    ($($tokens:tt)*) => {};
+}
+
+#[cfg(feature = "tracing")]
+mod trace;
+
+mod allocator;
+#[cfg(oneshot_allocator_api)]
+pub use allocator::AllocatorApi;
+pub use allocator::{AllocError, Allocator, Global};
+
+mod pool;
+pub use pool::{ChannelPool, PoolAllocator};
+
+mod batch;
+pub use batch::{channels, channels_array, BatchAllocator};
+
+mod race;
+pub use race::{race, RaceAllocator, RaceSender};
+
+mod raw;
+pub use raw::{RawReceiver, RawSender};
+
+#[cfg(feature = "std")]
+mod select;
+#[cfg(feature = "std")]
+pub use select::Select;
+
+#[cfg(feature = "std")]
+mod cancel;
+#[cfg(feature = "std")]
+pub use cancel::CancelToken;
+
+pub mod scoped;
+
+#[cfg(any(
+    all(feature = "ipc", target_os = "linux", not(oneshot_loom)),
+    all(feature = "futex", target_os = "linux", not(oneshot_loom))
+))]
+mod futex;
+
+#[cfg(all(feature = "ipc", target_os = "linux", not(oneshot_loom)))]
+pub mod ipc;
+
+pub mod parker;
+#[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+use parker::FutexPark;
+#[cfg(all(
+    feature = "std",
+    not(all(feature = "futex", target_os = "linux", not(oneshot_loom)))
+))]
+use parker::ThreadPark;
+use parker::{Park, ParkDeadline, Parker, TimedParker, UserPark};
+
+#[cfg(all(feature = "std", feature = "async"))]
+mod shared;
+#[cfg(all(feature = "std", feature = "async"))]
+pub use shared::SharedReceiver;
+
+#[cfg(all(feature = "std", feature = "async"))]
+mod group;
+#[cfg(all(feature = "std", feature = "async"))]
+pub use group::{NextMessage, ReceiverGroup};
+
+#[cfg(feature = "async")]
+pub mod timer;
+#[cfg(feature = "timer-thread")]
+use timer::ThreadTimer;
+#[cfg(feature = "async")]
+use timer::{Timeout, Timer};
 
 mod errors;
 // Wildcard imports are not nice. But since multiple errors have various conditional compilation,
@@ -189,17 +306,76 @@ pub use errors::*;
 
 /// Creates a new oneshot channel and returns the two endpoints, [`Sender`] and [`Receiver`].
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
-    // Allocate the channel on the heap and get the pointer.
+    channel_in(Global)
+}
+
+/// Creates a new oneshot channel with its shared state allocated in `alloc`, and returns the two
+/// endpoints, [`Sender`] and [`Receiver`].
+///
+/// The allocator is stored together with the channel state, and is used to free it again when
+/// both endpoints are gone.
+///
+/// Calls [`handle_alloc_error`](alloc::alloc::handle_alloc_error) if the allocation fails.
+pub fn channel_in<T, A: Allocator>(alloc: A) -> (Sender<T, A>, Receiver<T, A>) {
+    // Allocate the channel and get the pointer.
     // The last endpoint of the channel to be alive is responsible for freeing the channel
     // and dropping any object that might have been written to it.
-    let channel_ptr = NonNull::from(Box::leak(Box::new(Channel::new())));
+    let layout = allocation_layout::<T, A>();
+    let allocation = match alloc.allocate(layout) {
+        Ok(ptr) => ptr,
+        Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+    };
+    // SAFETY: the allocator returned memory fitting the layout of the allocation.
+    unsafe { channel_at(allocation, alloc) }
+}
+
+/// Allocates a channel holding `message` in the DISCONNECTED state, for a [`SendError`] of a send
+/// that had no channel of its own to leave the message in.
+pub(crate) fn unsent_channel<T>(message: T) -> NonNull<Channel<T>> {
+    let layout = allocation_layout::<T, Global>();
+    let allocation = match Global.allocate(layout) {
+        Ok(ptr) => ptr.cast::<Allocation<T, Global>>(),
+        Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+    };
+    // SAFETY: the allocator returned memory fitting the layout of the allocation.
+    unsafe {
+        allocation.as_ptr().write(Allocation {
+            channel: Channel::unsent(message),
+            alloc: Global,
+        })
+    };
+    // The channel is the first field of the `repr(C)` allocation.
+    allocation.cast()
+}
+
+/// Creates a new channel in the memory pointed to by `allocation`, and returns the two endpoints.
+///
+/// # Safety
+///
+/// `allocation` must be valid for writes of [`allocation_layout::<T, A>()`](allocation_layout),
+/// and it must be valid to free it with `alloc.deallocate`.
+pub(crate) unsafe fn channel_at<T, A: Allocator>(
+    allocation: NonNull<u8>,
+    alloc: A,
+) -> (Sender<T, A>, Receiver<T, A>) {
+    let allocation = allocation.cast::<Allocation<T, A>>();
+    allocation.as_ptr().write(Allocation {
+        channel: Channel::new(),
+        alloc,
+    });
+    // The channel is the first field of the `repr(C)` allocation.
+    let channel_ptr = allocation.cast::<Channel<T>>();
 
     (
         Sender {
             channel_ptr,
             _invariant: PhantomData,
+            _alloc: PhantomData,
+        },
+        Receiver {
+            channel_ptr,
+            _alloc: PhantomData,
         },
-        Receiver { channel_ptr },
     )
 }
 
@@ -208,8 +384,9 @@ pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
 /// Created and returned from the [`channel`] function.
 ///
 /// Can be used to send a message to the corresponding [`Receiver`].
-#[derive(Debug)]
-pub struct Sender<T> {
+///
+/// `A` is the [`Allocator`] holding the channel, see [`channel_in`].
+pub struct Sender<T, A: Allocator = Global> {
     channel_ptr: NonNull<Channel<T>>,
     // In reality we want contravariance, however we can't obtain that.
     //
@@ -228,6 +405,7 @@ pub struct Sender<T> {
     // If this type were covariant then we could safely extend lifetimes, which is not okay.
     // Hence, we enforce invariance.
     _invariant: PhantomData<fn(T) -> T>,
+    _alloc: PhantomData<A>,
 }
 
 /// Receiving end of a oneshot channel.
@@ -239,24 +417,27 @@ pub struct Sender<T> {
 ///
 /// This type implement [`IntoFuture`](core::future::IntoFuture) when the `async` feature is enabled.
 /// This allows awaiting it directly in an async context.
-#[derive(Debug)]
-pub struct Receiver<T> {
+///
+/// `A` is the [`Allocator`] holding the channel, see [`channel_in`].
+pub struct Receiver<T, A: Allocator = Global> {
     // Covariance is the right choice here. Consider the example presented in Sender, and you'll
     // see that if we replaced `rx` instead then we would get the expected behavior
     channel_ptr: NonNull<Channel<T>>,
+    _alloc: PhantomData<A>,
 }
 
-unsafe impl<T: Send> Send for Sender<T> {}
+// The allocator is only ever used by the endpoint freeing the channel, which can be on any thread.
+unsafe impl<T: Send, A: Allocator + Send> Send for Sender<T, A> {}
 
 // SAFETY: The only methods that assumes there is only a single reference to the sender
 // takes `self` by value, guaranteeing that there is only one reference to the sender at
 // the time it is called.
-unsafe impl<T: Sync> Sync for Sender<T> {}
+unsafe impl<T: Sync, A: Allocator> Sync for Sender<T, A> {}
 
-unsafe impl<T: Send> Send for Receiver<T> {}
-impl<T> Unpin for Receiver<T> {}
+unsafe impl<T: Send, A: Allocator + Send> Send for Receiver<T, A> {}
+impl<T, A: Allocator> Unpin for Receiver<T, A> {}
 
-impl<T> Sender<T> {
+impl<T, A: Allocator> Sender<T, A> {
     /// Sends `message` over the channel to the corresponding [`Receiver`].
     ///
     /// Returns an error if the receiver has already been dropped. The message can
@@ -270,7 +451,24 @@ impl<T> Sender<T> {
     /// depends on your executor. If this method returns a `SendError`, please mind that dropping
     /// the error involves running any drop implementation on the message type, and freeing the
     /// channel's heap allocation, which might or might not be lock-free.
-    pub fn send(self, message: T) -> Result<(), SendError<T>> {
+    pub fn send(self, message: T) -> Result<(), SendError<T, A>> {
+        // EMPTY + 1 = MESSAGE
+        // RECEIVING + 1 = UNPARKING
+        // CLOSED + 1 = REJECTED
+        // DISCONNECTED + 1 = invalid, however this state is only observed by the SendError
+        self.publish(message, |state| state.fetch_add(1, Release))
+    }
+
+    /// Writes `message` to the channel and makes it visible to the receiver with `update`, which
+    /// moves the state from EMPTY, RECEIVING or CLOSED to the state after it, MESSAGE, UNPARKING
+    /// or REJECTED, with release ordering. `update` returns which of those states the channel was
+    /// in, or DISCONNECTED.
+    #[inline(always)]
+    fn publish(
+        self,
+        message: T,
+        update: impl FnOnce(&AtomicState) -> State,
+    ) -> Result<(), SendError<T, A>> {
         let channel_ptr = self.channel_ptr;
 
         // Don't run our Drop implementation if send was called, any cleanup now happens here
@@ -281,24 +479,35 @@ impl<T> Sender<T> {
         // does not free the channel.
         let channel = unsafe { channel_ptr.as_ref() };
 
+        // Drop any waker stored by `Sender::closed` or `Sender::wait_closed`. The sender stops waiting for the receiver here.
+        // SAFETY: we are the sender.
+        #[cfg(any(feature = "std", feature = "async"))]
+        unsafe {
+            channel.unregister_sender_waker()
+        };
+
         // Write the message into the channel on the heap.
         // SAFETY: The receiver only ever accesses this memory location if we are in the MESSAGE
         // state, and since we're responsible for setting that state, we can guarantee that we have
         // exclusive access to this memory location to perform this write.
         unsafe { channel.write_message(message) };
 
+        // The receiver may free the channel as soon as the state changes, so events about the send
+        // use a copy of its trace.
+        #[cfg(feature = "tracing")]
+        let trace = channel.trace.clone();
+
         // Set the state to signal there is a message on the channel.
         // ORDERING: we use release ordering to ensure the write of the message is visible to the
         // receiving thread. The EMPTY and DISCONNECTED branches do not observe any shared state,
         // and thus we do not need acquire ordering. The RECEIVING branch manages synchronization
         // independent of this operation.
-        //
-        // EMPTY + 1 = MESSAGE
-        // RECEIVING + 1 = UNPARKING
-        // DISCONNECTED + 1 = invalid, however this state is never observed
-        match channel.state.fetch_add(1, Release) {
+        match update(&channel.state) {
             // The receiver is alive and has not started waiting. Send done.
-            EMPTY => Ok(()),
+            EMPTY => {
+                channel_event!(trace, TRACE, outcome = "stored", "message sent");
+                Ok(())
+            }
             // The receiver is waiting. Wake it up so it can return the message.
             RECEIVING => {
                 // ORDERING: Synchronizes with the write of the waker to memory, and prevents the
@@ -326,22 +535,96 @@ impl<T> Sender<T> {
                 // the channel allocation. However, we took ownership of the channel out of
                 // that allocation, and freeing the channel does not drop the waker since the
                 // waker is wrapped in MaybeUninit. Therefore this data is valid regardless of
-                // whether or not the receive has completed by this point.
-                waker.unpark();
+                // whether or not the receive has completed by this point. A receiver blocked on
+                // the state with a futex has no waker, and was already woken up.
+                if let Some(waker) = waker {
+                    waker.unpark();
+                }
 
+                channel_event!(trace, TRACE, outcome = "woke receiver", "message sent");
                 Ok(())
             }
             // The receiver was already dropped. The error is responsible for freeing the channel.
-            // SAFETY: since the receiver disconnected it will no longer access `channel_ptr`, so
-            // we can transfer exclusive ownership of the channel's resources to the error.
-            // Moreover, since we just placed the message in the channel, the channel contains a
-            // valid message.
-            DISCONNECTED => Err(unsafe { SendError::new(channel_ptr) }),
+            DISCONNECTED => {
+                // ORDERING: Synchronize with the receiver's last accesses to the channel before
+                // it disconnected, since the error is going to free the channel.
+                fence(Acquire);
+
+                // SAFETY: since the receiver disconnected it will no longer access `channel_ptr`,
+                // so we can transfer exclusive ownership of the channel's resources to the error.
+                // Moreover, since we just placed the message in the channel, the channel contains
+                // a valid message.
+                channel_event!(trace, DEBUG, outcome = "receiver gone", "message sent");
+                Err(unsafe { SendError::new(channel_ptr) })
+            }
+            // The receiver has closed the channel, but is still alive. The error takes ownership
+            // of the message, and the REJECTED state tells the receiver to not touch it.
+            // SAFETY: we just placed the message in the channel, so it contains a valid message.
+            // The error does not free the channel while the receiver is alive.
+            CLOSED => {
+                channel_event!(trace, DEBUG, outcome = "receiver gone", "message sent");
+                Err(unsafe { SendError::new(channel_ptr) })
+            }
             _ => unreachable!(),
         }
     }
 
-    /// Returns true if the associated [`Receiver`] has been dropped.
+    /// Sends the message returned from `f` over the channel to the corresponding [`Receiver`].
+    /// Calls `f` only if the receiver is still there to receive the message, so producing the
+    /// message can be skipped when nobody will receive it.
+    ///
+    /// Returns [`SendWithError::Closed`] with `f`, without calling it, if the receiver has
+    /// already been dropped or closed. The receiver can still go away while `f` runs, in which
+    /// case [`SendWithError::Send`] holds the message, like [`Sender::send`] would.
+    ///
+    /// The message is written to the channel only once `f` has returned, and made visible to
+    /// the receiver after that, like with [`Sender::send`]. So the receiver never observes a
+    /// partially written message, even if `f` takes a long time or panics. While `f` runs the
+    /// channel is reserved for the message: the receiver waits for it like for any other send,
+    /// and [`Receiver::state`] reports [`ChannelState::SenderWriting`]. If `f` panics the
+    /// reservation is released and the sender is dropped without sending.
+    pub fn send_with<F: FnOnce() -> T>(self, f: F) -> Result<(), SendWithError<T, F, A>> {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it. Note that if the receiver disconnects it
+        // does not free the channel.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        if channel.start_writing().is_err() {
+            // Our Drop implementation frees the channel if the receiver was dropped.
+            return Err(SendWithError::Closed(f));
+        }
+
+        // Release the reservation again if `f` panics, before the sender is dropped.
+        let writing = Writing(self);
+        let message = f();
+        // SAFETY: the guard is forgotten, so the sender is only moved out of it once.
+        let sender = unsafe { ptr::read(&writing.0) };
+        mem::forget(writing);
+
+        sender
+            .publish_reserved(message)
+            .map_err(SendWithError::Send)
+    }
+
+    /// Like `Sender::send`, on a channel reserved with `Channel::start_writing`.
+    #[inline]
+    fn publish_reserved(self, message: T) -> Result<(), SendError<T, A>> {
+        // Clearing the WRITING bit and adding one is subtracting WRITING - MESSAGE.
+        // WRITING - 15 = MESSAGE
+        // WRITING_RECEIVING - 15 = UNPARKING
+        // WRITING_CLOSED - 15 = REJECTED
+        // DISCONNECTED - 15 = invalid, however this state is only observed by the SendError
+        self.publish(message, |state| {
+            match state.fetch_sub(WRITING - MESSAGE, Release) {
+                WRITING => EMPTY,
+                WRITING_RECEIVING => RECEIVING,
+                WRITING_CLOSED => CLOSED,
+                state => state,
+            }
+        })
+    }
+
+    /// Returns true if the associated [`Receiver`] has been dropped or closed.
     ///
     /// If true is returned, a future call to send is guaranteed to return an error.
     pub fn is_closed(&self) -> bool {
@@ -353,15 +636,221 @@ impl<T> Sender<T> {
         // ORDERING: We *chose* a Relaxed ordering here as it sufficient to
         // enforce the method's contract: "if true is returned, a future
         // call to send is guaranteed to return an error."
-        channel.state.load(Relaxed) == DISCONNECTED
+        matches!(channel.state.load(Relaxed), DISCONNECTED | CLOSED)
+    }
+
+    /// Returns what the channel is doing right now. Meant for debugging and diagnostics, the
+    /// state can change as soon as it has been read.
+    ///
+    /// The sender sees [`ChannelState::Empty`] until the receiver starts waiting, is dropped or
+    /// closes the channel.
+    pub fn state(&self) -> ChannelState {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it. Note that if the receiver disconnects it
+        // does not free the channel.
+        unsafe { self.channel_ptr.as_ref() }.public_state()
+    }
+
+    /// Returns true if the associated [`Receiver`] has started waiting for the message. That is,
+    /// it has entered a blocking receive method or been polled as a future.
+    ///
+    /// Once true has been returned, it stays true. Even if the receiver later gives up waiting.
+    /// This allows lazy producers to hold off computing the message until someone wants it.
+    #[cfg(any(feature = "std", feature = "async"))]
+    pub fn is_receiver_waiting(&self) -> bool {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it. Note that if the receiver disconnects it
+        // does not free the channel.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        // ORDERING: Relaxed is enough since the flag is never cleared, and there is no other
+        // memory we access based on it.
+        channel.sender_state.load(Relaxed) & RECEIVER_DEMAND != 0
+    }
+
+    /// Returns a future that completes when the associated [`Receiver`] has been dropped or
+    /// closed.
+    ///
+    /// This allows aborting the work of producing a message if nobody is going to receive it,
+    /// for example by racing this future against the computation in a `select!`.
+    /// Completes immediately if the receiver is already gone. Once the future has completed,
+    /// [`Sender::is_closed`] returns true.
+    #[cfg(feature = "async")]
+    pub fn closed(&mut self) -> Closed<'_, T, A> {
+        Closed { sender: self }
+    }
+
+    /// Polls whether the associated [`Receiver`] has been dropped or closed. Returns
+    /// `Poll::Ready(())` if it has. Otherwise the waker in `cx` is registered to be woken up when
+    /// the receiver is dropped or closed.
+    ///
+    /// Only the waker from the latest call to this method, or [`Sender::poll_demand`], is kept.
+    /// This is the poll based version of [`Sender::closed`].
+    #[cfg(feature = "async")]
+    pub fn poll_closed(&mut self, cx: &mut task::Context<'_>) -> Poll<()> {
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
+        self.poll_receiver_flags(cx, RECEIVER_CLOSED)
+    }
+
+    /// Returns a future that completes when the associated [`Receiver`] starts waiting for the
+    /// message, or is dropped or closed. Resolves to the value of [`Sender::is_receiver_waiting`].
+    ///
+    /// This allows a lazy producer to only start computing the message once someone awaits it.
+    #[cfg(feature = "async")]
+    pub fn demand(&mut self) -> Demand<'_, T, A> {
+        Demand { sender: self }
+    }
+
+    /// Polls whether the associated [`Receiver`] has started waiting for the message, or has been
+    /// dropped. Returns `Poll::Ready(true)` if the receiver is waiting and `Poll::Ready(false)` if
+    /// it was dropped or closed without ever waiting. Otherwise the waker in `cx` is registered to be woken
+    /// up when either happens.
+    ///
+    /// Only the waker from the latest call to this method, or [`Sender::poll_closed`], is kept.
+    /// This is the poll based version of [`Sender::demand`].
+    #[cfg(feature = "async")]
+    pub fn poll_demand(&mut self, cx: &mut task::Context<'_>) -> Poll<bool> {
+        self.poll_receiver_flags(cx, RECEIVER_DEMAND | RECEIVER_CLOSED)
+            .map(|()| self.is_receiver_waiting())
+    }
+
+    /// Blocks the current thread until the associated [`Receiver`] has been dropped or closed.
+    ///
+    /// Returns immediately if the receiver is already gone. Once this method has returned,
+    /// [`Sender::is_closed`] returns true. This is the thread blocking version of
+    /// `Sender::closed`.
+    #[cfg(feature = "std")]
+    pub fn wait_closed(&mut self) {
+        if !self.is_closed() {
+            self.wait_receiver_flags(RECEIVER_CLOSED, None);
+        }
+    }
+
+    /// Like [`Sender::wait_closed`], but will not block longer than `timeout`. Returns true if
+    /// the receiver was dropped or closed, and false if the timeout was reached first.
+    ///
+    /// If the supplied `timeout` is so large that Rust's `Instant` type can't represent this point
+    /// in the future this falls back to an indefinitely blocking wait.
+    #[cfg(feature = "std")]
+    pub fn wait_closed_timeout(&mut self, timeout: Duration) -> bool {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.wait_closed_deadline(deadline),
+            None => {
+                self.wait_closed();
+                true
+            }
+        }
+    }
+
+    /// Like [`Sender::wait_closed`], but will not block longer than until `deadline`. Returns
+    /// true if the receiver was dropped or closed, and false if the deadline was reached first.
+    #[cfg(feature = "std")]
+    pub fn wait_closed_deadline(&mut self, deadline: Instant) -> bool {
+        self.is_closed() || self.wait_receiver_flags(RECEIVER_CLOSED, Some(deadline))
+    }
+
+    /// Blocks the current thread until the associated [`Receiver`] starts waiting for the
+    /// message, or is dropped or closed. Returns the value of [`Sender::is_receiver_waiting`].
+    ///
+    /// This is the thread blocking version of `Sender::demand`.
+    #[cfg(feature = "std")]
+    pub fn wait_for_demand(&mut self) -> bool {
+        self.wait_receiver_flags(RECEIVER_DEMAND | RECEIVER_CLOSED, None);
+        self.is_receiver_waiting()
+    }
+
+    /// Registers the task in `cx` to be woken up when the receiver sets any of `flags`, unless
+    /// any of them are already set.
+    #[cfg(feature = "async")]
+    fn poll_receiver_flags(&mut self, cx: &mut task::Context<'_>, flags: u8) -> Poll<()> {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it. Note that if the receiver disconnects it
+        // does not free the channel.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        // ORDERING: The flags do not guard any memory, so Relaxed is enough.
+        if channel.sender_state.load(Relaxed) & flags == 0
+            // SAFETY: we are the sender.
+            && unsafe { channel.register_sender_waker(ReceiverWaker::task_waker(cx), flags) }
+        {
+            return Poll::Pending;
+        }
+        self.finish_waiting_for_receiver();
+        Poll::Ready(())
     }
 
+    /// Parks the current thread until the receiver sets any of `flags`, or until `deadline`.
+    /// Returns false if the deadline was reached first.
+    #[cfg(feature = "std")]
+    fn wait_receiver_flags(&mut self, flags: u8, deadline: Option<Instant>) -> bool {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it. Note that if the receiver disconnects it
+        // does not free the channel.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        loop {
+            // ORDERING: The flags do not guard any memory, so Relaxed is enough.
+            let state = channel.sender_state.load(Relaxed);
+            if state & flags != 0 {
+                break;
+            }
+            // We have no waker registered. Either because this is the first iteration, or because
+            // the receiver took it when setting a flag we are not waiting for. Register one.
+            // SAFETY: we are the sender.
+            if state & SENDER_WAKER == 0
+                && !unsafe { channel.register_sender_waker(ReceiverWaker::current_thread(), flags) }
+            {
+                break;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) => thread::park_timeout(timeout),
+                    None => {
+                        // SAFETY: we are the sender.
+                        unsafe { channel.unregister_sender_waker() };
+                        if channel.sender_state.load(Relaxed) & flags == 0 {
+                            return false;
+                        }
+                        break;
+                    }
+                },
+            }
+        }
+        self.finish_waiting_for_receiver();
+        true
+    }
+
+    /// Called when the receiver has set one of the flags the sender waits for. If the flag is
+    /// RECEIVER_CLOSED, the receiver changes the channel state right after setting it. We busy
+    /// loop until it has since we know the receiver is done very soon. This guarantees
+    /// [`Sender::is_closed`] returns true after waiting for the receiver to close.
+    #[cfg(any(feature = "std", feature = "async"))]
+    fn finish_waiting_for_receiver(&self) {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it. Note that if the receiver disconnects it
+        // does not free the channel.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        if channel.sender_state.load(Relaxed) & RECEIVER_CLOSED != 0 {
+            while !self.is_closed() {
+                hint::spin_loop();
+            }
+        }
+    }
+}
+
+impl<T> Sender<T> {
     /// Consumes the Sender, returning a raw pointer to the channel on the heap.
     ///
     /// This is intended to simplify using oneshot channels with some FFI code. The only safe thing
     /// to do with the returned pointer is to later reconstruct the Sender with [Sender::from_raw].
     /// Memory will leak if the Sender is never reconstructed.
     pub fn into_raw(self) -> *mut () {
+        // SAFETY: the channel is alive as long as we are.
+        unsafe { raw::leave(self.channel_ptr, raw::End::Sender) };
         let raw = self.channel_ptr.as_ptr() as *mut ();
         mem::forget(self);
         raw
@@ -374,15 +863,22 @@ impl<T> Sender<T> {
     /// This pointer must have come from [`Sender<T>::into_raw`] with the same message type, `T`.
     /// At most one Sender must exist for a channel at any point in time.
     /// Constructing multiple Senders from the same raw pointer leads to undefined behavior.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds or with the `checked-raw` feature, if the channel sends another type than
+    /// `T`, or if the Sender was already reconstructed. See [`RawSender`].
     pub unsafe fn from_raw(raw: *mut ()) -> Self {
+        raw::enter::<T>(raw, raw::End::Sender);
         Self {
             channel_ptr: NonNull::new_unchecked(raw as *mut Channel<T>),
             _invariant: PhantomData,
+            _alloc: PhantomData,
         }
     }
 }
 
-impl<T> Drop for Sender<T> {
+impl<T, A: Allocator> Drop for Sender<T, A> {
     fn drop(&mut self) {
         // SAFETY: The receiver only ever frees the channel if we are in the MESSAGE or
         // DISCONNECTED states. If we are in the MESSAGE state, then we called
@@ -392,6 +888,13 @@ impl<T> Drop for Sender<T> {
         // free the channel.
         let channel = unsafe { self.channel_ptr.as_ref() };
 
+        // Drop any waker stored by `Sender::closed` or `Sender::wait_closed`.
+        // SAFETY: we are the sender.
+        #[cfg(any(feature = "std", feature = "async"))]
+        unsafe {
+            channel.unregister_sender_waker()
+        };
+
         // Set the channel state to disconnected and read what state the receiver was in
         // ORDERING: we don't need release ordering here since there are no modifications we
         // need to make visible to other thread, and the Err(RECEIVING) branch handles
@@ -399,10 +902,14 @@ impl<T> Drop for Sender<T> {
         //
         // EMPTY ^ 001 = DISCONNECTED
         // RECEIVING ^ 001 = UNPARKING
+        // CLOSED ^ 001 = CLOSED_DISCONNECTED
         // DISCONNECTED ^ 001 = EMPTY (invalid), but this state is never observed
+        channel_event!(channel.trace, DEBUG, "sender dropped without sending");
         match channel.state.fetch_xor(0b001, Relaxed) {
             // The receiver has not started waiting, nor is it dropped.
             EMPTY => (),
+            // The receiver has closed the channel, but is still alive. It frees the channel.
+            CLOSED => (),
             // The receiver is waiting. Wake it up so it can detect that the channel disconnected.
             RECEIVING => {
                 // See comments in Sender::send
@@ -418,27 +925,46 @@ impl<T> Drop for Sender<T> {
 
                 // The Acquire ordering above ensures that the write of the DISCONNECTED state
                 // happens-before unparking the receiver.
-                waker.unpark();
+                if let Some(waker) = waker {
+                    waker.unpark();
+                }
             }
             // The receiver was already dropped. We are responsible for freeing the channel.
             DISCONNECTED => {
+                // ORDERING: Synchronize with the receiver's last accesses to the channel before
+                // it disconnected.
+                fence(Acquire);
+
                 // SAFETY: when the receiver switches the state to DISCONNECTED they have received
                 // the message or will no longer be trying to receive the message, and have
                 // observed that the sender is still alive, meaning that we're responsible for
                 // freeing the channel allocation.
-                unsafe { dealloc(self.channel_ptr) };
+                unsafe { dealloc::<T, A>(self.channel_ptr) };
             }
             _ => unreachable!(),
         }
     }
 }
 
-impl<T> Receiver<T> {
+/// A sender that has reserved the channel in [`Sender::send_with`]. Releases the reservation if
+/// it is dropped, which only happens if producing the message panics. The sender is then
+/// dropped like any other.
+struct Writing<T, A: Allocator>(Sender<T, A>);
+
+impl<T, A: Allocator> Drop for Writing<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: the sender is alive, so the channel is valid.
+        unsafe { self.0.channel_ptr.as_ref() }.stop_writing();
+    }
+}
+
+impl<T, A: Allocator> Receiver<T, A> {
     /// Checks if there is a message in the channel without blocking. Returns:
     ///  * `Ok(message)` if there was a message in the channel.
     ///  * `Err(Empty)` if the [`Sender`] is alive, but has not yet sent a message.
-    ///  * `Err(Disconnected)` if the [`Sender`] was dropped before sending anything or if the
-    ///    message has already been extracted by a previous receive call.
+    ///  * `Err(Disconnected)` if the [`Sender`] was dropped before sending anything, if the
+    ///    message has already been extracted by a previous receive call, or if the receiver was
+    ///    [closed](Receiver::close) before a message was sent.
     ///
     /// If a message is returned, the channel is disconnected and any subsequent receive operation
     /// using this receiver will return an error.
@@ -460,13 +986,17 @@ impl<T> Receiver<T> {
                 // we don't need to make any side effects visible to it
                 channel.state.store(DISCONNECTED, Relaxed);
 
+                channel_event!(channel.trace, TRACE, "message received");
+
                 // SAFETY: we are in the MESSAGE state so the message is present
                 Ok(unsafe { channel.take_message() })
             }
-            EMPTY => Err(TryRecvError::Empty),
-            DISCONNECTED => Err(TryRecvError::Disconnected),
+            EMPTY | WRITING => Err(TryRecvError::Empty),
+            DISCONNECTED | CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED => {
+                Err(TryRecvError::Disconnected)
+            }
             #[cfg(feature = "async")]
-            RECEIVING | UNPARKING => Err(TryRecvError::Empty),
+            RECEIVING | UNPARKING | WRITING_RECEIVING => Err(TryRecvError::Empty),
             _ => unreachable!(),
         }
     }
@@ -484,17 +1014,76 @@ impl<T> Receiver<T> {
     ///
     /// If a sent message has already been extracted from this channel this method will return an
     /// error.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called after this receiver has been polled asynchronously.
     #[cfg(feature = "std")]
     pub fn recv(self) -> Result<T, RecvError> {
+        let parker = self.thread_park();
+        self.recv_parked(&parker)
+    }
+
+    /// Like `Receiver::recv`, but blocks the current thread with `parker` instead of with the
+    /// standard library. Available without the `std` feature. See the [`parker`] module.
+    pub fn recv_with<P: Parker>(self, parker: &P) -> Result<T, RecvError> {
+        self.recv_parked(&UserPark(parker))
+    }
+
+    /// How the blocking receive methods without a parker block the current thread.
+    #[cfg(all(
+        feature = "std",
+        not(all(feature = "futex", target_os = "linux", not(oneshot_loom)))
+    ))]
+    #[inline]
+    fn thread_park(&self) -> ThreadPark {
+        ThreadPark
+    }
+
+    /// How the blocking receive methods without a parker block the current thread. Waits on the
+    /// channel state with a futex.
+    #[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+    #[inline]
+    fn thread_park(&self) -> FutexPark {
+        // SAFETY: the receiver is alive, so the channel is valid.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+        FutexPark(&channel.state)
+    }
+
+    #[inline]
+    fn recv_parked<P: Park>(self, parker: &P) -> Result<T, RecvError> {
+        // The receive may free the channel, so the outcome is recorded with a copy of its trace.
+        #[cfg(feature = "tracing")]
+        let trace = self.trace().clone();
+        let result = self.recv_parked_untraced(parker);
+        #[cfg(feature = "tracing")]
+        trace.received(&result);
+        result
+    }
+
+    #[cfg(feature = "tracing")]
+    fn trace(&self) -> &trace::ChannelTrace {
+        // SAFETY: the receiver is alive, so the channel is valid.
+        &unsafe { self.channel_ptr.as_ref() }.trace
+    }
+
+    #[inline(always)]
+    fn recv_parked_untraced<P: Park>(self, parker: &P) -> Result<T, RecvError> {
         // Note that we don't need to worry about changing the state to disconnected or setting the
         // state to an invalid value at any point in this function because we take ownership of
         // self, and this function does not exit until the message has been received or both side
         // of the channel are inactive and cleaned up.
 
+        // SAFETY: the receiver is alive, so the channel is valid.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+        match channel.state.load(Relaxed) {
+            // A closed receiver never gets a message, but the sender or its SendError might still
+            // be alive. Let our Drop implementation sort out who frees the channel.
+            CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED => return Err(RecvError),
+            // The receiver was `Future::poll`ed prior to this call. Take back the task waker so
+            // we can store our thread waker instead.
+            // SAFETY: we are the receiver.
+            #[cfg(feature = "async")]
+            RECEIVING | UNPARKING | WRITING_RECEIVING => unsafe { channel.reclaim_waker() },
+            _ => (),
+        }
+
         let channel_ptr = self.channel_ptr;
 
         // Don't run our Drop implementation. This consuming recv method is responsible for freeing.
@@ -508,9 +1097,15 @@ impl<T> Receiver<T> {
 
         // ORDERING: we use acquire ordering to synchronize with the write of the message in the
         // case that it's available
-        match channel.state.load(Acquire) {
-            // The sender is alive but has not sent anything yet. We prepare to park.
-            EMPTY => {
+        let mut state = channel.state.load(Acquire);
+        if matches!(state, EMPTY | WRITING) {
+            // SAFETY: we are the receiver.
+            unsafe { channel.spin_while_empty(parker, &mut state) };
+        }
+        match state {
+            // The sender is alive but has not sent anything yet, or is still producing the
+            // message in `Sender::send_with`. We prepare to park.
+            EMPTY | WRITING => {
                 // Conditionally add a delay here to help the tests trigger the edge cases where
                 // the sender manages to be dropped or send something before we are able to store
                 // our waker object in the channel.
@@ -520,20 +1115,16 @@ impl<T> Receiver<T> {
                 // Write our waker instance to the channel.
                 // SAFETY: we are not yet in the RECEIVING state, meaning that the sender will not
                 // try to access the waker until it sees the state set to RECEIVING below
-                unsafe { channel.write_waker(ReceiverWaker::current_thread()) };
-
-                // Switch the state to RECEIVING. We need to do this in one atomic step in case the
-                // sender disconnected or sent the message while we wrote the waker to memory. We
-                // don't need to do a compare exchange here however because if the original state
-                // was not EMPTY, then the sender has either finished sending the message or is
-                // being dropped, so the RECEIVING state will never be observed after we return.
-                // ORDERING: we use release ordering so the sender can synchronize with our writing
-                // of the waker to memory. The individual branches handle any additional
-                // synchronizaton
-                match channel.state.swap(RECEIVING, Release) {
+                unsafe { channel.write_waker(parker.receiver_waker()) };
+
+                // Switch the state to RECEIVING. This fails if the sender disconnected or sent the
+                // message while we wrote the waker to memory.
+                // ORDERING: see `Channel::start_receiving`. The individual branches handle any
+                // additional synchronizaton
+                match channel.start_receiving() {
                     // We stored our waker, now we park until the sender has changed the state
-                    EMPTY => loop {
-                        thread::park();
+                    Ok(()) => loop {
+                        parker.park();
 
                         // ORDERING: synchronize with the write of the message
                         match channel.state.load(Acquire) {
@@ -544,7 +1135,7 @@ impl<T> Receiver<T> {
 
                                 // SAFETY: the Sender delegates the responsibility of deallocating
                                 // the channel to us upon sending the message
-                                unsafe { dealloc(channel_ptr) };
+                                unsafe { dealloc::<T, A>(channel_ptr) };
 
                                 break Ok(message);
                             }
@@ -552,17 +1143,17 @@ impl<T> Receiver<T> {
                             DISCONNECTED => {
                                 // SAFETY: the Sender doesn't deallocate the channel allocation in
                                 // its drop implementation if we're receiving
-                                unsafe { dealloc(channel_ptr) };
+                                unsafe { dealloc::<T, A>(channel_ptr) };
 
                                 break Err(RecvError);
                             }
                             // State did not change, spurious wakeup, park again.
-                            RECEIVING | UNPARKING => (),
+                            RECEIVING | UNPARKING | WRITING_RECEIVING => (),
                             _ => unreachable!(),
                         }
                     },
                     // The sender sent the message while we prepared to park.
-                    MESSAGE => {
+                    Err(MESSAGE) => {
                         // ORDERING: Synchronize with the write of the message. This branch is
                         // unlikely to be taken, so it's likely more efficient to use a fence here
                         // instead of AcqRel ordering on the RMW operation
@@ -578,12 +1169,12 @@ impl<T> Receiver<T> {
 
                         // SAFETY: the Sender delegates the responsibility of deallocating the
                         // channel to us upon sending the message
-                        unsafe { dealloc(channel_ptr) };
+                        unsafe { dealloc::<T, A>(channel_ptr) };
 
                         Ok(message)
                     }
                     // The sender was dropped before sending anything while we prepared to park.
-                    DISCONNECTED => {
+                    Err(DISCONNECTED) => {
                         // SAFETY: we started in the empty state and the sender switched us to the
                         // disconnected state. It does not take the waker when it does this so we
                         // need to drop it.
@@ -591,7 +1182,7 @@ impl<T> Receiver<T> {
 
                         // SAFETY: the sender does not deallocate the channel if it switches from
                         // empty to disconnected so we need to free the allocation
-                        unsafe { dealloc(channel_ptr) };
+                        unsafe { dealloc::<T, A>(channel_ptr) };
 
                         Err(RecvError)
                     }
@@ -605,7 +1196,7 @@ impl<T> Receiver<T> {
 
                 // SAFETY: we are already in the message state so the sender has been forgotten
                 // and it's our job to clean up resources
-                unsafe { dealloc(channel_ptr) };
+                unsafe { dealloc::<T, A>(channel_ptr) };
 
                 Ok(message)
             }
@@ -613,13 +1204,10 @@ impl<T> Receiver<T> {
             DISCONNECTED => {
                 // SAFETY: the sender does not deallocate the channel if it switches from empty to
                 // disconnected so we need to free the allocation
-                unsafe { dealloc(channel_ptr) };
+                unsafe { dealloc::<T, A>(channel_ptr) };
 
                 Err(RecvError)
             }
-            // The receiver must have been `Future::poll`ed prior to this call.
-            #[cfg(feature = "async")]
-            RECEIVING | UNPARKING => panic!("{}", RECEIVER_USED_SYNC_AND_ASYNC_ERROR),
             _ => unreachable!(),
         }
     }
@@ -630,32 +1218,45 @@ impl<T> Receiver<T> {
     ///
     /// If a message is returned, the channel is disconnected and any subsequent receive operation
     /// using this receiver will return an error.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called after this receiver has been polled asynchronously.
     #[cfg(feature = "std")]
     pub fn recv_ref(&self) -> Result<T, RecvError> {
-        self.start_recv_ref(RecvError, |channel| {
+        let parker = self.thread_park();
+        self.recv_ref_parked(&parker)
+    }
+
+    /// Like `Receiver::recv_ref`, but blocks the current thread with `parker` instead of with
+    /// the standard library. Available without the `std` feature. See the [`parker`] module.
+    pub fn recv_ref_with<P: Parker>(&self, parker: &P) -> Result<T, RecvError> {
+        self.recv_ref_parked(&UserPark(parker))
+    }
+
+    #[inline]
+    fn recv_ref_parked<P: Park>(&self, parker: &P) -> Result<T, RecvError> {
+        // SAFETY: the wait succeeded, so the channel is in the message state.
+        let result = self
+            .wait_parked(parker)
+            .map(|()| unsafe { self.take_waited_message() });
+        #[cfg(feature = "tracing")]
+        self.trace().received(&result);
+        result
+    }
+
+    /// Blocks until the channel holds the message, and leaves it there. Used by `recv_ref` and
+    /// the peek methods.
+    #[inline]
+    fn wait_parked<P: Park>(&self, parker: &P) -> Result<(), RecvError> {
+        self.start_wait(parker, RecvError, |channel| {
             loop {
-                thread::park();
+                parker.park();
 
                 // ORDERING: we use acquire ordering to synchronize with the write of the message
                 match channel.state.load(Acquire) {
                     // The sender sent the message while we were parked.
-                    // We take the message and mark the channel disconnected.
-                    MESSAGE => {
-                        // ORDERING: the sender is inactive at this point so we don't need to make
-                        // any reads or writes visible to the sending thread
-                        channel.state.store(DISCONNECTED, Relaxed);
-
-                        // SAFETY: we were just in the message state so the message is valid
-                        break Ok(unsafe { channel.take_message() });
-                    }
+                    MESSAGE => break Ok(()),
                     // The sender was dropped while we were parked.
                     DISCONNECTED => break Err(RecvError),
                     // State did not change, spurious wakeup, park again.
-                    RECEIVING | UNPARKING => (),
+                    RECEIVING | UNPARKING | WRITING_RECEIVING => (),
                     _ => unreachable!(),
                 }
             }
@@ -673,10 +1274,6 @@ impl<T> Receiver<T> {
     ///
     /// If the supplied `timeout` is so large that Rust's `Instant` type can't represent this point
     /// in the future this falls back to an indefinitely blocking receive operation.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called after this receiver has been polled asynchronously.
     #[cfg(feature = "std")]
     pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
         match Instant::now().checked_add(timeout) {
@@ -693,110 +1290,178 @@ impl<T> Receiver<T> {
     ///
     /// If a message is returned, the channel is disconnected and any subsequent receive operation
     /// using this receiver will return an error.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called after this receiver has been polled asynchronously.
     #[cfg(feature = "std")]
     pub fn recv_deadline(&self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        let parker = self.thread_park();
+        self.recv_deadline_parked(&parker, deadline)
+    }
+
+    /// Like `Receiver::recv_deadline`, but blocks the current thread with `parker` instead of
+    /// with the standard library, and uses the clock of `parker` for the deadline. Available
+    /// without the `std` feature. See the [`parker`] module.
+    pub fn recv_deadline_with<P: TimedParker>(
+        &self,
+        parker: &P,
+        deadline: P::Instant,
+    ) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline_parked(&UserPark(parker), deadline)
+    }
+
+    #[inline]
+    fn recv_deadline_parked<P: ParkDeadline>(
+        &self,
+        parker: &P,
+        deadline: P::Instant,
+    ) -> Result<T, RecvTimeoutError> {
+        // SAFETY: the wait succeeded, so the channel is in the message state.
+        let result = self
+            .wait_deadline_parked(parker, deadline)
+            .map(|()| unsafe { self.take_waited_message() });
+        #[cfg(feature = "tracing")]
+        self.trace().received(&result);
+        result
+    }
+
+    /// Blocks until the channel holds the message, and leaves it there, or until `deadline`.
+    /// Used by `recv_deadline` and the peek methods.
+    #[inline]
+    fn wait_deadline_parked<P: ParkDeadline>(
+        &self,
+        parker: &P,
+        deadline: P::Instant,
+    ) -> Result<(), RecvTimeoutError> {
         /// # Safety
         ///
         /// If the sender is unparking us after a message send, the message must already have been
         /// written to the channel and an acquire memory barrier issued before calling this function
         #[cold]
-        unsafe fn wait_for_unpark<T>(channel: &Channel<T>) -> Result<T, RecvTimeoutError> {
+        unsafe fn wait_for_unpark<T>(
+            channel: &Channel<T>,
+            parker: &impl Park,
+        ) -> Result<(), RecvTimeoutError> {
             loop {
-                thread::park();
+                parker.park();
 
                 // ORDERING: The callee has already synchronized with any message write
                 match channel.state.load(Relaxed) {
-                    MESSAGE => {
-                        // ORDERING: the sender has been dropped, so this update only
-                        // needs to be visible to us
-                        channel.state.store(DISCONNECTED, Relaxed);
-                        break Ok(channel.take_message());
-                    }
+                    MESSAGE => break Ok(()),
                     DISCONNECTED => break Err(RecvTimeoutError::Disconnected),
-                    // The sender is still unparking us. We continue on the empty state here since
-                    // the current implementation eagerly sets the state to EMPTY upon timeout.
-                    EMPTY => (),
+                    // The sender is still unparking us.
+                    UNPARKING => (),
                     _ => unreachable!(),
                 }
             }
         }
 
-        self.start_recv_ref(RecvTimeoutError::Disconnected, |channel| {
+        self.start_wait(parker, RecvTimeoutError::Disconnected, |channel| {
             loop {
-                match deadline.checked_duration_since(Instant::now()) {
-                    Some(timeout) => {
-                        thread::park_timeout(timeout);
-
-                        // ORDERING: synchronize with the write of the message
-                        match channel.state.load(Acquire) {
-                            // The sender sent the message while we were parked.
-                            MESSAGE => {
-                                // ORDERING: the sender has been `mem::forget`-ed so this update
-                                // only needs to be visible to us.
-                                channel.state.store(DISCONNECTED, Relaxed);
-
-                                // SAFETY: we either are in the message state or were just in the
-                                // message state
-                                break Ok(unsafe { channel.take_message() });
-                            }
-                            // The sender was dropped while we were parked.
-                            DISCONNECTED => break Err(RecvTimeoutError::Disconnected),
-                            // State did not change, spurious wakeup, park again.
-                            RECEIVING | UNPARKING => (),
-                            _ => unreachable!(),
-                        }
+                if parker.park_until(&deadline) {
+                    // ORDERING: synchronize with the write of the message
+                    match channel.state.load(Acquire) {
+                        // The sender sent the message while we were parked.
+                        MESSAGE => break Ok(()),
+                        // The sender was dropped while we were parked.
+                        DISCONNECTED => break Err(RecvTimeoutError::Disconnected),
+                        // State did not change, spurious wakeup, park again.
+                        RECEIVING | UNPARKING | WRITING_RECEIVING => (),
+                        _ => unreachable!(),
                     }
-                    None => {
-                        // ORDERING: synchronize with the write of the message
-                        match channel.state.swap(EMPTY, Acquire) {
-                            // We reached the end of the timeout without receiving a message
-                            RECEIVING => {
-                                // SAFETY: we were in the receiving state and are now in the empty
-                                // state, so the sender has not and will not try to read the waker,
-                                // so we have exclusive access to drop it.
-                                unsafe { channel.drop_waker() };
-
-                                break Err(RecvTimeoutError::Timeout);
-                            }
-                            // The sender sent the message while we were parked.
-                            MESSAGE => {
-                                // Same safety and ordering as the Some branch
-
-                                channel.state.store(DISCONNECTED, Relaxed);
-                                break Ok(unsafe { channel.take_message() });
-                            }
-                            // The sender was dropped while we were parked.
-                            DISCONNECTED => {
-                                // ORDERING: we were originally in the disconnected state meaning
-                                // that the sender is inactive and no longer observing the state,
-                                // so we only need to change it back to DISCONNECTED for if the
-                                // receiver is dropped or a recv* method is called again
-                                channel.state.store(DISCONNECTED, Relaxed);
-
-                                break Err(RecvTimeoutError::Disconnected);
-                            }
-                            // The sender sent the message and started unparking us
-                            UNPARKING => {
-                                // We were in the UNPARKING state and are now in the EMPTY state.
-                                // We wait to be properly unparked and to observe if the sender
-                                // sets MESSAGE or DISCONNECTED state.
-                                // SAFETY: The load above has synchronized with any message write.
-                                break unsafe { wait_for_unpark(channel) };
-                            }
-                            _ => unreachable!(),
+                } else {
+                    // We reached the deadline. Stop receiving, unless the sender got to the waker
+                    // first.
+                    // ORDERING: see `Channel::stop_receiving`.
+                    match channel.stop_receiving() {
+                        // We reached the end of the timeout without receiving a message
+                        Ok(()) => {
+                            // SAFETY: we left the receiving state, so the sender has not and will
+                            // not try to read the waker, so we have exclusive access to drop it.
+                            unsafe { channel.drop_waker() };
+
+                            break Err(RecvTimeoutError::Timeout);
+                        }
+                        // The sender sent the message while we were parked.
+                        Err(MESSAGE) => break Ok(()),
+                        // The sender was dropped while we were parked.
+                        Err(DISCONNECTED) => break Err(RecvTimeoutError::Disconnected),
+                        // The sender sent the message and started unparking us
+                        Err(UNPARKING) => {
+                            // We wait to be properly unparked and to observe if the sender
+                            // sets MESSAGE or DISCONNECTED state.
+                            // SAFETY: The load above has synchronized with any message write.
+                            break unsafe { wait_for_unpark(channel, parker) };
                         }
+                        _ => unreachable!(),
                     }
                 }
             }
         })
     }
 
+    /// Returns a future completing with the message, like awaiting the receiver, but that gives
+    /// up once `timeout` has passed. The future yields:
+    ///  * `Ok(message)` if there was a message in the channel before the timeout was reached.
+    ///  * `Err(Timeout)` if no message arrived on the channel before the timeout was reached.
+    ///  * `Err(Disconnected)` if the sender was dropped before sending anything or if the message
+    ///    has already been extracted by a previous receive call.
+    ///
+    /// The receiver is only borrowed, so after a timeout it can still be used to receive a message
+    /// sent later.
+    ///
+    /// Sleeps with the background thread of [`timer::ThreadTimer`], which works with any async
+    /// runtime. Use [`Receiver::timeout_with`] to sleep with another [`Timer`].
+    #[cfg(feature = "timer-thread")]
+    pub fn timeout(&mut self, timeout: Duration) -> Timeout<'_, T, A, timer::ThreadSleep> {
+        self.timeout_with(&ThreadTimer, timeout)
+    }
+
+    /// Like [`Receiver::timeout`], but gives up at `deadline` instead of after a timeout.
+    ///
+    /// Sleeps with the background thread of [`timer::ThreadTimer`]. Use
+    /// [`Receiver::deadline_with`] to sleep with another [`Timer`].
+    #[cfg(feature = "timer-thread")]
+    pub fn deadline(&mut self, deadline: Instant) -> Timeout<'_, T, A, timer::ThreadSleep> {
+        self.deadline_with(&ThreadTimer, deadline)
+    }
+
+    /// Returns a future completing with the message, like awaiting the receiver, but that gives
+    /// up once the given `timer` has slept for `timeout`. The future yields:
+    ///  * `Ok(message)` if there was a message in the channel before the timeout was reached.
+    ///  * `Err(Timeout)` if no message arrived on the channel before the timeout was reached.
+    ///  * `Err(Disconnected)` if the sender was dropped before sending anything or if the message
+    ///    has already been extracted by a previous receive call.
+    ///
+    /// The receiver is only borrowed, so after a timeout it can still be used to receive a message
+    /// sent later. See the [`timer`] module for the available timers.
+    #[cfg(feature = "async")]
+    pub fn timeout_with<Tm: Timer>(
+        &mut self,
+        timer: &Tm,
+        timeout: core::time::Duration,
+    ) -> Timeout<'_, T, A, Tm::Sleep> {
+        Timeout::new(self, timer.sleep(timeout))
+    }
+
+    /// Like [`Receiver::timeout_with`], but gives up at `deadline`, on the clock of the `timer`,
+    /// instead of after a timeout.
+    #[cfg(feature = "async")]
+    pub fn deadline_with<Tm: Timer>(
+        &mut self,
+        timer: &Tm,
+        deadline: Tm::Instant,
+    ) -> Timeout<'_, T, A, Tm::Sleep> {
+        Timeout::new(self, timer.sleep_until(deadline))
+    }
+
+    /// Turns this receiver into a [`SharedReceiver`], which can be cloned so that any number of
+    /// threads and tasks can wait for the message.
+    #[cfg(all(feature = "std", feature = "async"))]
+    pub fn shared(self) -> SharedReceiver<T, A> {
+        SharedReceiver::new(self)
+    }
+
     /// Returns true if the associated [`Sender`] was dropped before sending a message. Or if
-    /// the message has already been received.
+    /// the message has already been received. Or if this receiver was [closed](Receiver::close)
+    /// before a message was sent.
     ///
     /// If `true` is returned, all future calls to receive methods are guaranteed to return
     /// a disconnected error. And future calls to this method is guaranteed to also return `true`.
@@ -811,7 +1476,82 @@ impl<T> Receiver<T> {
         // enforce the method's contract. Once true has been observed, it will remain true.
         // However, if false is observed, the sender might have just disconnected but this thread
         // has not observed it yet.
-        channel.state.load(Relaxed) == DISCONNECTED
+        matches!(
+            channel.state.load(Relaxed),
+            DISCONNECTED | CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED
+        )
+    }
+
+    /// Closes the channel without dropping the receiver. Any subsequent [`Sender::send`] returns
+    /// an error and [`Sender::is_closed`] returns true.
+    ///
+    /// A message sent before the channel was closed is still in the channel, and can be received
+    /// as usual. Check [`Receiver::has_message`] or call [`Receiver::try_recv`] after closing to
+    /// drain it. If no message was sent, all receive methods return a disconnected error.
+    ///
+    /// If the receiver has been polled as a future, the registered waker is dropped.
+    ///
+    /// Closing while the sender produces the message in [`Sender::send_with`] does not wait for
+    /// it. The message is not delivered, `send_with` returns it in [`SendWithError::Send`].
+    pub fn close(&mut self) {
+        // SAFETY: the existence of the `self` parameter serves as a certificate that the receiver
+        // is still alive, meaning that even if the sender was dropped then it would have observed
+        // the fact that we're still alive and left the responsibility of deallocating the
+        // channel to us, so `self.channel` is valid
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        // Tell a sender waiting in `Sender::closed` or `Sender::wait_closed` that we are closing.
+        // The waker is woken up after the state change, so the sender observes the new state.
+        // SAFETY: we are the receiver.
+        #[cfg(any(feature = "std", feature = "async"))]
+        let sender_waker = unsafe { channel.notify_sender(RECEIVER_CLOSED) };
+
+        // ORDERING: The sender does not need to synchronize with anything we have written, and
+        // we do not read any memory written by the sender. The UNPARKING branch has already
+        // synchronized with the write of the message by the time it returns.
+        let mut state = EMPTY;
+        loop {
+            let closed = match state {
+                EMPTY | RECEIVING => CLOSED,
+                // The sender is producing the message. It finds the channel closed once it is
+                // done, and frees the message with the error it returns.
+                WRITING | WRITING_RECEIVING => WRITING_CLOSED,
+                // The sender is currently waking us up. The message stays in the channel.
+                #[cfg(feature = "async")]
+                UNPARKING => {
+                    while channel.state.load(Acquire) == UNPARKING {
+                        hint::spin_loop();
+                    }
+                    break;
+                }
+                // The message was already sent and stays in the channel. Or the sender is gone, or
+                // we were already closed. In all those cases there is nothing more to do.
+                MESSAGE | DISCONNECTED | CLOSED | REJECTED | CLOSED_DISCONNECTED
+                | WRITING_CLOSED => break,
+                _ => unreachable!(),
+            };
+            match channel
+                .state
+                .compare_exchange(state, closed, Relaxed, Relaxed)
+            {
+                Ok(_) => {
+                    // We have been polled. We took back the waker at the same time as closing.
+                    // SAFETY: We wrote the waker in a previous call to poll, and the sender will
+                    // not access it after we left the RECEIVING state.
+                    #[cfg(feature = "async")]
+                    if matches!(state, RECEIVING | WRITING_RECEIVING) {
+                        unsafe { channel.drop_waker() };
+                    }
+                    break;
+                }
+                Err(actual) => state = actual,
+            }
+        }
+
+        #[cfg(any(feature = "std", feature = "async"))]
+        if let Some(waker) = sender_waker {
+            waker.unpark();
+        }
     }
 
     /// Returns true if there is a message in the channel, ready to be received.
@@ -831,19 +1571,131 @@ impl<T> Receiver<T> {
         channel.state.load(Acquire) == MESSAGE
     }
 
-    /// Begins the process of receiving on the channel by reference. If the message is already
+    /// Returns what the channel is doing right now. Meant for debugging and diagnostics, the
+    /// state can change as soon as it has been read, unless it is [`ChannelState::Disconnected`].
+    /// A [`ChannelState::Closed`] channel becomes disconnected when the sender sent a message
+    /// anyway, and the returned error is dropped or consumed.
+    ///
+    /// [`ChannelState::ReceiverWaitingAsync`] means the receiver was polled as a future, and
+    /// still holds a task waker in the channel, even if the future is no longer polled.
+    pub fn state(&self) -> ChannelState {
+        // SAFETY: the existence of the `self` parameter serves as a certificate that the receiver
+        // is still alive, meaning that even if the sender was dropped then it would have observed
+        // the fact that we're still alive and left the responsibility of deallocating the
+        // channel to us, so `self.channel` is valid
+        unsafe { self.channel_ptr.as_ref() }.public_state()
+    }
+
+    /// Checks if there is a message in the channel without blocking, and without taking it out of
+    /// the channel. Returns:
+    ///  * `Ok(&message)` if there was a message in the channel.
+    ///  * `Err(Empty)` if the [`Sender`] is alive, but has not yet sent a message.
+    ///  * `Err(Disconnected)` if the [`Sender`] was dropped before sending anything, if the
+    ///    message has already been extracted by a previous receive call, or if the receiver was
+    ///    [closed](Receiver::close) before a message was sent.
+    ///
+    /// The message stays in the channel, and a later receive call returns it. This method takes
+    /// `&mut self` so that the message can't be received while the returned reference is alive.
+    ///
+    /// This method is completely lock-free and wait-free.
+    pub fn try_peek(&mut self) -> Result<&T, TryRecvError> {
+        // SAFETY: The channel will not be freed while this method is still running.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        // ORDERING: we use acquire ordering to synchronize with the store of the message.
+        match channel.state.load(Acquire) {
+            // SAFETY: we are in the message state so the message is present. Once in the message
+            // state the sender is gone, and only the receiver, which we borrow mutably, can take
+            // the message out of the channel.
+            MESSAGE => Ok(unsafe { channel.message().assume_init_ref() }),
+            EMPTY | WRITING => Err(TryRecvError::Empty),
+            DISCONNECTED | CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED => {
+                Err(TryRecvError::Disconnected)
+            }
+            #[cfg(feature = "async")]
+            RECEIVING | UNPARKING | WRITING_RECEIVING => Err(TryRecvError::Empty),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Like [`Receiver::try_peek`], but blocks for up to `timeout` waiting for the message.
+    /// Returns:
+    ///  * `Ok(&message)` if there was a message in the channel before the timeout was reached.
+    ///  * `Err(Timeout)` if no message arrived on the channel before the timeout was reached.
+    ///  * `Err(Disconnected)` if the sender was dropped before sending anything or if the message
+    ///    has already been extracted by a previous receive call.
+    ///
+    /// The message stays in the channel, and a later receive call returns it.
+    ///
+    /// If the supplied `timeout` is so large that Rust's `Instant` type can't represent this point
+    /// in the future this falls back to an indefinitely blocking peek operation.
+    #[cfg(feature = "std")]
+    pub fn peek_timeout(&mut self, timeout: Duration) -> Result<&T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.peek_deadline(deadline),
+            None => {
+                let parker = self.thread_park();
+                self.wait_parked(&parker)
+                    .map_err(|_| RecvTimeoutError::Disconnected)?;
+                // SAFETY: the wait succeeded, so the channel is in the message state.
+                Ok(unsafe { self.waited_message() })
+            }
+        }
+    }
+
+    /// Like [`Receiver::try_peek`], but blocks until `deadline` waiting for the message.
+    /// See [`Receiver::peek_timeout`] for details.
+    #[cfg(feature = "std")]
+    pub fn peek_deadline(&mut self, deadline: Instant) -> Result<&T, RecvTimeoutError> {
+        let parker = self.thread_park();
+        self.wait_deadline_parked(&parker, deadline)?;
+        // SAFETY: the wait succeeded, so the channel is in the message state.
+        Ok(unsafe { self.waited_message() })
+    }
+
+    /// Returns a reference to the message a wait left in the channel.
+    ///
+    /// # Safety
+    ///
+    /// The channel must be in the MESSAGE state.
+    #[cfg(feature = "std")]
+    unsafe fn waited_message(&mut self) -> &T {
+        // SAFETY: once in the message state the sender is gone, and only the receiver, which we
+        // borrow mutably, can take the message out of the channel.
+        self.channel_ptr.as_ref().message().assume_init_ref()
+    }
+
+    /// Takes the message a wait left in the channel, and marks the channel disconnected.
+    ///
+    /// # Safety
+    ///
+    /// The channel must be in the MESSAGE state.
+    unsafe fn take_waited_message(&self) -> T {
+        let channel = self.channel_ptr.as_ref();
+
+        // ORDERING: the sender is gone, so this update only needs to be visible to us.
+        channel.state.store(DISCONNECTED, Relaxed);
+
+        // SAFETY: we are in the message state so the message is valid
+        channel.take_message()
+    }
+
+    /// Begins the process of waiting for the message by reference. If the message is already
     /// ready, or the sender has disconnected, then this function will return the appropriate
     /// Result immediately. Otherwise, it will write the waker to memory, check to see if the
     /// sender has finished or disconnected again, and then will call `finish`. `finish` is
     /// thus responsible for cleaning up the channel's resources appropriately before it returns,
     /// such as destroying the waker, for instance.
-    #[cfg(feature = "std")]
+    ///
+    /// Returns `Ok` with the channel in the MESSAGE state. The message is left in the channel,
+    /// for the caller to take it or hand out a reference to it.
     #[inline]
-    fn start_recv_ref<E>(
+    fn start_wait<E, P: Park>(
         &self,
+        parker: &P,
         disconnected_error: E,
-        finish: impl FnOnce(&Channel<T>) -> Result<T, E>,
-    ) -> Result<T, E> {
+        finish: impl FnOnce(&Channel<T>) -> Result<(), E>,
+    ) -> Result<(), E> {
         // SAFETY: the existence of the `self` parameter serves as a certificate that the receiver
         // is still alive, meaning that even if the sender was dropped then it would have observed
         // the fact that we're still alive and left the responsibility of deallocating the
@@ -851,9 +1703,15 @@ impl<T> Receiver<T> {
         let channel = unsafe { self.channel_ptr.as_ref() };
 
         // ORDERING: synchronize with the write of the message
-        match channel.state.load(Acquire) {
-            // The sender is alive but has not sent anything yet. We prepare to park.
-            EMPTY => {
+        let mut state = channel.state.load(Acquire);
+        if matches!(state, EMPTY | WRITING) {
+            // SAFETY: we are the receiver.
+            unsafe { channel.spin_while_empty(parker, &mut state) };
+        }
+        match state {
+            // The sender is alive but has not sent anything yet, or is still producing the
+            // message in `Sender::send_with`. We prepare to park.
+            EMPTY | WRITING => {
                 // Conditionally add a delay here to help the tests trigger the edge cases where
                 // the sender manages to be dropped or send something before we are able to store
                 // our waker object in the channel.
@@ -863,19 +1721,14 @@ impl<T> Receiver<T> {
                 // Write our waker instance to the channel.
                 // SAFETY: we are not yet in the RECEIVING state, meaning that the sender will not
                 // try to access the waker until it sees the state set to RECEIVING below
-                unsafe { channel.write_waker(ReceiverWaker::current_thread()) };
+                unsafe { channel.write_waker(parker.receiver_waker()) };
 
-                // ORDERING: we use release ordering on success so the sender can synchronize with
-                // our write of the waker. We use relaxed ordering on failure since the sender does
-                // not need to synchronize with our write and the individual match arms handle any
+                // ORDERING: see `Channel::start_receiving`. The individual match arms handle any
                 // additional synchronization
-                match channel
-                    .state
-                    .compare_exchange(EMPTY, RECEIVING, Release, Relaxed)
-                {
+                match channel.start_receiving() {
                     // We stored our waker, now we delegate to the callback to finish the receive
                     // operation
-                    Ok(_) => finish(channel),
+                    Ok(()) => finish(channel),
                     // The sender sent the message while we prepared to finish
                     Err(MESSAGE) => {
                         // See comments in `recv` for ordering and safety
@@ -884,13 +1737,7 @@ impl<T> Receiver<T> {
 
                         unsafe { channel.drop_waker() };
 
-                        // ORDERING: the sender has been `mem::forget`-ed so this update only
-                        // needs to be visible to us
-                        channel.state.store(DISCONNECTED, Relaxed);
-
-                        // SAFETY: The MESSAGE state tells us there is a correctly initialized
-                        // message
-                        Ok(unsafe { channel.take_message() })
+                        Ok(())
                     }
                     // The sender was dropped before sending anything while we prepared to park.
                     Err(DISCONNECTED) => {
@@ -901,30 +1748,35 @@ impl<T> Receiver<T> {
                     _ => unreachable!(),
                 }
             }
-            // The sender sent the message. We take the message and mark the channel disconnected.
-            MESSAGE => {
-                // ORDERING: the sender has been `mem::forget`-ed so this update only needs to be
-                // visible to us
-                channel.state.store(DISCONNECTED, Relaxed);
-
-                // SAFETY: we are in the message state so the message is valid
-                Ok(unsafe { channel.take_message() })
-            }
+            // The sender sent the message.
+            MESSAGE => Ok(()),
             // The sender was dropped before sending anything, or we already received the message.
-            DISCONNECTED => Err(disconnected_error),
-            // The receiver must have been `Future::poll`ed prior to this call.
+            // Or we closed the channel before a message was sent.
+            DISCONNECTED | CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED => {
+                Err(disconnected_error)
+            }
+            // The receiver was `Future::poll`ed prior to this call. Take back the task waker so
+            // we can store our thread waker instead, then start over.
             #[cfg(feature = "async")]
-            RECEIVING | UNPARKING => panic!("{}", RECEIVER_USED_SYNC_AND_ASYNC_ERROR),
+            RECEIVING | UNPARKING | WRITING_RECEIVING => {
+                // SAFETY: we are the receiver.
+                unsafe { channel.reclaim_waker() };
+                self.start_wait(parker, disconnected_error, finish)
+            }
             _ => unreachable!(),
         }
     }
+}
 
+impl<T> Receiver<T> {
     /// Consumes the Receiver, returning a raw pointer to the channel on the heap.
     ///
     /// This is intended to simplify using oneshot channels with some FFI code. The only safe thing
     /// to do with the returned pointer is to later reconstruct the Receiver with
     /// [Receiver::from_raw]. Memory will leak if the Receiver is never reconstructed.
     pub fn into_raw(self) -> *mut () {
+        // SAFETY: the channel is alive as long as we are.
+        unsafe { raw::leave(self.channel_ptr, raw::End::Receiver) };
         let raw = self.channel_ptr.as_ptr() as *mut ();
         mem::forget(self);
         raw
@@ -937,15 +1789,22 @@ impl<T> Receiver<T> {
     /// This pointer must have come from [`Receiver<T>::into_raw`] with the same message type, `T`.
     /// At most one Receiver must exist for a channel at any point in time.
     /// Constructing multiple Receivers from the same raw pointer leads to undefined behavior.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds or with the `checked-raw` feature, if the channel sends another type than
+    /// `T`, or if the Receiver was already reconstructed. See [`RawSender`].
     pub unsafe fn from_raw(raw: *mut ()) -> Self {
+        raw::enter::<T>(raw, raw::End::Receiver);
         Self {
             channel_ptr: NonNull::new_unchecked(raw as *mut Channel<T>),
+            _alloc: PhantomData,
         }
     }
 }
 
 #[cfg(feature = "async")]
-impl<T> core::future::Future for Receiver<T> {
+impl<T, A: Allocator> core::future::Future for Receiver<T, A> {
     type Output = Result<T, RecvError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
@@ -956,25 +1815,23 @@ impl<T> core::future::Future for Receiver<T> {
         let channel = unsafe { self.channel_ptr.as_ref() };
 
         // ORDERING: we use acquire ordering to synchronize with the store of the message.
-        match channel.state.load(Acquire) {
+        let poll = match channel.state.load(Acquire) {
             // The sender is alive but has not sent anything yet.
-            EMPTY => {
+            EMPTY | WRITING => {
+                // Let a sender waiting in `Sender::demand` know that we are waiting.
+                // SAFETY: we are the receiver.
+                unsafe { channel.notify_demand() };
+
                 // SAFETY: We can't be in the forbidden states, and no waker in the channel.
                 unsafe { channel.write_async_waker(cx) }
             }
             // We were polled again while waiting for the sender. Replace the waker with the new one.
-            RECEIVING => {
-                // ORDERING: We use relaxed ordering on both success and failure since we have not
-                // written anything above that must be released, and the individual match arms
-                // handle any additional synchronization.
-                match channel
-                    .state
-                    .compare_exchange(RECEIVING, EMPTY, Relaxed, Relaxed)
-                {
+            RECEIVING | WRITING_RECEIVING => {
+                // ORDERING: see `Channel::stop_receiving`. We have not written anything above
+                // that must be released.
+                match channel.stop_receiving() {
                     // We successfully changed the state back to EMPTY. Replace the waker.
-                    // This is the most likely branch to be taken, which is why we don't use any
-                    // memory barriers in the compare_exchange above.
-                    Ok(_) => {
+                    Ok(()) => {
                         // SAFETY: We wrote the waker in a previous call to poll. We do not need
                         // a memory barrier since the previous write here was by ourselves.
                         unsafe { channel.drop_waker() };
@@ -1013,7 +1870,10 @@ impl<T> core::future::Future for Receiver<T> {
                 Poll::Ready(Ok(unsafe { channel.take_message() }))
             }
             // The sender was dropped before sending anything, or we already received the message.
-            DISCONNECTED => Poll::Ready(Err(RecvError)),
+            // Or we closed the channel before a message was sent.
+            DISCONNECTED | CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED => {
+                Poll::Ready(Err(RecvError))
+            }
             // The sender has observed the RECEIVING state and is currently reading the waker from
             // a previous poll. We need to loop here until we observe the MESSAGE or DISCONNECTED
             // state. We busy loop here since we know the sender is done very soon.
@@ -1034,44 +1894,65 @@ impl<T> core::future::Future for Receiver<T> {
                 }
             },
             _ => unreachable!(),
+        };
+        #[cfg(feature = "tracing")]
+        if let Poll::Ready(result) = &poll {
+            channel.trace.received(result);
         }
+        poll
     }
 }
 
-impl<T> Drop for Receiver<T> {
+impl<T, A: Allocator> Drop for Receiver<T, A> {
     fn drop(&mut self) {
         // SAFETY: since the receiving side is still alive the sender would have observed that and
         // left deallocating the channel allocation to us.
         let channel = unsafe { self.channel_ptr.as_ref() };
 
+        // Tell a sender waiting in `Sender::closed` or `Sender::wait_closed` that we are going away. This must happen
+        // before the state swap below, since the sender might free the channel after that.
+        // The waker is woken up after the swap, so the sender observes the new state when it runs.
+        // SAFETY: we are the receiver.
+        #[cfg(any(feature = "std", feature = "async"))]
+        let sender_waker = unsafe { channel.notify_sender(RECEIVER_CLOSED) };
+
         // Set the channel state to disconnected and read what state the receiver was in
-        match channel.state.swap(DISCONNECTED, Acquire) {
-            // The sender has not sent anything, nor is it dropped.
-            EMPTY => (),
+        // ORDERING: we use release ordering so the sender can synchronize with our accesses to
+        // the channel above before it frees it.
+        channel_event!(channel.trace, TRACE, "receiver dropped");
+        match channel.state.swap(DISCONNECTED, AcqRel) {
+            // The sender has not sent anything, nor is it dropped. Or it is producing the message
+            // in `Sender::send_with`, and frees the channel once it observes our DISCONNECTED state.
+            EMPTY | WRITING => (),
             // The sender already sent something. We must drop it, and free the channel.
             MESSAGE => {
+                channel_event!(channel.trace, DEBUG, "unreceived message dropped");
+
                 // SAFETY: we are in the message state so the message is initialized
                 unsafe { channel.drop_message() };
 
                 // SAFETY: see safety comment at top of function
-                unsafe { dealloc(self.channel_ptr) };
+                unsafe { dealloc::<T, A>(self.channel_ptr) };
             }
             // The receiver has been polled.
             #[cfg(feature = "async")]
-            RECEIVING => {
+            RECEIVING | WRITING_RECEIVING => {
                 // TODO: figure this out when async is fixed
                 unsafe { channel.drop_waker() };
             }
             // The sender was already dropped. We are responsible for freeing the channel.
-            DISCONNECTED => {
+            DISCONNECTED | CLOSED_DISCONNECTED => {
                 // SAFETY: see safety comment at top of function
-                unsafe { dealloc(self.channel_ptr) };
+                unsafe { dealloc::<T, A>(self.channel_ptr) };
             }
+            // We closed the channel, and the sender is either still alive, or a SendError owns the
+            // message it tried to send. Either way they free the channel when they see our
+            // DISCONNECTED state.
+            CLOSED | REJECTED | WRITING_CLOSED => (),
             // This receiver was previously polled, so the channel was in the RECEIVING state.
             // But the sender has observed the RECEIVING state and is currently reading the waker
             // to wake us up. We need to loop here until we observe the MESSAGE or DISCONNECTED state.
             // We busy loop here since we know the sender is done very soon.
-            #[cfg(any(feature = "std", feature = "async"))]
             UNPARKING => {
                 loop {
                     hint::spin_loop();
@@ -1088,32 +1969,158 @@ impl<T> Drop for Receiver<T> {
                     }
                 }
                 // SAFETY: see safety comment at top of function
-                unsafe { dealloc(self.channel_ptr) };
+                unsafe { dealloc::<T, A>(self.channel_ptr) };
             }
             _ => unreachable!(),
         }
+
+        #[cfg(any(feature = "std", feature = "async"))]
+        if let Some(waker) = sender_waker {
+            waker.unpark();
+        }
+    }
+}
+
+/// Future returned from [`Sender::closed`]. Completes when the [`Receiver`] has been dropped.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct Closed<'a, T, A: Allocator = Global> {
+    sender: &'a mut Sender<T, A>,
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator> core::future::Future for Closed<'_, T, A> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.sender.poll_closed(cx)
     }
 }
 
+/// Future returned from [`Sender::demand`]. Completes when the [`Receiver`] starts waiting for
+/// the message, or is dropped.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct Demand<'a, T, A: Allocator = Global> {
+    sender: &'a mut Sender<T, A>,
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator> core::future::Future for Demand<'_, T, A> {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.sender.poll_demand(cx)
+    }
+}
+
+impl<T, A: Allocator> fmt::Debug for Sender<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("channel_ptr", &self.channel_ptr)
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, A: Allocator> fmt::Debug for Receiver<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("channel_ptr", &self.channel_ptr)
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A snapshot of what a channel is doing, from [`Sender::state`] and [`Receiver::state`].
+///
+/// More detailed than [`Sender::is_closed`] and [`Receiver::has_message`], to help debugging
+/// a receiver that never completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChannelState {
+    /// Both endpoints are alive. No message has been sent, and the receiver is not waiting.
+    Empty,
+    /// The sender is producing the message in [`Sender::send_with`], and the receiver is not
+    /// waiting.
+    SenderWriting,
+    /// A message has been sent and not yet received.
+    Message,
+    /// No message has been sent, and the receiver is blocking a thread waiting for it, in a
+    /// blocking receive method or a `Select`.
+    ReceiverWaitingSync,
+    /// No message has been sent, and the receiver has been polled as a future and waits for its
+    /// task to be woken up.
+    ReceiverWaitingAsync,
+    /// The sender is sending the message, or being dropped, and is waking the receiver up.
+    Unparking,
+    /// The sender was dropped without sending, the message has already been received, or the
+    /// receiver was dropped.
+    Disconnected,
+    /// The receiver [closed](Receiver::close) the channel before a message was sent.
+    Closed,
+}
+
 /// All the values that the `Channel::state` field can have during the lifetime of a channel.
+/// As well as the flags that make up the `Channel::sender_state` field.
 mod states {
     // These values are very explicitly chosen so that we can replace some cmpxchg calls with
     // fetch_* calls.
 
+    /// The type of `Channel::state`.
+    #[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+    pub type State = u32;
+    #[cfg(not(all(feature = "futex", target_os = "linux", not(oneshot_loom))))]
+    pub type State = u8;
+
     /// The initial channel state. Active while both endpoints are still alive, no message has been
     /// sent, and the receiver is not receiving.
-    pub const EMPTY: u8 = 0b011;
+    pub const EMPTY: State = 0b011;
     /// A message has been sent to the channel, but the receiver has not yet read it.
-    pub const MESSAGE: u8 = 0b100;
+    pub const MESSAGE: State = 0b100;
     /// No message has yet been sent on the channel, but the receiver is currently receiving.
-    pub const RECEIVING: u8 = 0b000;
-    #[cfg(any(feature = "std", feature = "async"))]
-    pub const UNPARKING: u8 = 0b001;
+    pub const RECEIVING: State = 0b000;
+    /// The sender has observed the RECEIVING state and is taking the waker to wake the receiver up.
+    pub const UNPARKING: State = 0b001;
     /// The channel has been closed. This means that either the sender or receiver has been dropped,
     /// or the message sent to the channel has already been received. Since this is a oneshot
     /// channel, it is disconnected after the one message it is supposed to hold has been
     /// transmitted.
-    pub const DISCONNECTED: u8 = 0b010;
+    pub const DISCONNECTED: State = 0b010;
+    /// The receiver has closed the channel with `Receiver::close` before a message was sent.
+    /// Both endpoints are still alive.
+    pub const CLOSED: State = 0b0111;
+    /// The sender tried to send a message on a CLOSED channel. The message was written to the
+    /// channel, but is owned by the returned `SendError`. The receiver must not touch it.
+    pub const REJECTED: State = 0b1000;
+    /// The sender was dropped after the receiver closed the channel. To the receiver this is
+    /// the same as DISCONNECTED.
+    pub const CLOSED_DISCONNECTED: State = 0b0110;
+    /// The sender has reserved the channel in `Sender::send_with` and is producing the message.
+    /// To the receiver this is the same as EMPTY. The WRITING states are EMPTY, RECEIVING and
+    /// CLOSED with the 0b1_0000 bit set, and the sender only clears that bit again.
+    pub const WRITING: State = 0b1_0011;
+    /// The receiver started waiting for the message while the sender was WRITING.
+    pub const WRITING_RECEIVING: State = 0b1_0000;
+    /// The receiver closed the channel while the sender was WRITING.
+    pub const WRITING_CLOSED: State = 0b1_0111;
+
+    /// Set in `Channel::sender_state` while the sender has a waker stored in
+    /// `Channel::sender_waker`. Whoever clears this flag takes ownership of the stored waker.
+    #[cfg(any(feature = "std", feature = "async"))]
+    pub const SENDER_WAKER: u8 = 0b01;
+    /// Set in `Channel::sender_state` by the receiver when it is dropped. Never cleared.
+    #[cfg(any(feature = "std", feature = "async"))]
+    pub const RECEIVER_CLOSED: u8 = 0b10;
+    /// Set in `Channel::sender_state` by the receiver the first time it starts waiting for the
+    /// message. Never cleared.
+    #[cfg(any(feature = "std", feature = "async"))]
+    pub const RECEIVER_DEMAND: u8 = 0b100;
+    /// Set in `Channel::sender_state` by the receiver while the waker it stores in the channel is
+    /// a task waker. Only read to tell `ChannelState::ReceiverWaitingAsync` apart.
+    #[cfg(feature = "async")]
+    pub const RECEIVER_ASYNC: u8 = 0b1000;
+
 }
 use states::*;
 
@@ -1123,18 +2130,57 @@ use states::*;
 /// * The message in the channel. This memory is uninitialized until the message is sent.
 /// * The waker instance for the thread or task that is currently receiving on this channel.
 ///   This memory is uninitialized until the receiver starts receiving.
+/// * Flags telling the sender what the receiver is up to, and the waker instance for the task
+///   waiting on the sender side. This memory is uninitialized until the sender starts waiting.
+#[cfg_attr(any(debug_assertions, feature = "checked-raw"), repr(C))]
 struct Channel<T> {
-    state: AtomicU8,
+    // Must be the first field, so it can be found without knowing `T`.
+    #[cfg(any(debug_assertions, feature = "checked-raw"))]
+    raw_check: raw::RawCheck,
+    state: AtomicState,
+    #[cfg(feature = "tracing")]
+    trace: trace::ChannelTrace,
     message: UnsafeCell<MaybeUninit<T>>,
-    waker: UnsafeCell<MaybeUninit<ReceiverWaker>>,
+    // `None` while the receiver blocks on the state with a futex.
+    waker: UnsafeCell<MaybeUninit<Option<ReceiverWaker>>>,
+    #[cfg(any(feature = "std", feature = "async"))]
+    sender_state: AtomicU8,
+    #[cfg(any(feature = "std", feature = "async"))]
+    sender_waker: UnsafeCell<MaybeUninit<ReceiverWaker>>,
 }
 
 impl<T> Channel<T> {
     pub fn new() -> Self {
         Self {
-            state: AtomicU8::new(EMPTY),
+            #[cfg(any(debug_assertions, feature = "checked-raw"))]
+            raw_check: raw::RawCheck::new::<T>(),
+            state: AtomicState::new(EMPTY),
+            #[cfg(feature = "tracing")]
+            trace: trace::ChannelTrace::new(),
             message: UnsafeCell::new(MaybeUninit::uninit()),
             waker: UnsafeCell::new(MaybeUninit::uninit()),
+            #[cfg(any(feature = "std", feature = "async"))]
+            sender_state: AtomicU8::new(0),
+            #[cfg(any(feature = "std", feature = "async"))]
+            sender_waker: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Creates a channel already holding `message`, as if it was sent after the receiver was
+    /// dropped. Nobody receives on it, so it records no `tracing` events.
+    fn unsent(message: T) -> Self {
+        Self {
+            #[cfg(any(debug_assertions, feature = "checked-raw"))]
+            raw_check: raw::RawCheck::new::<T>(),
+            state: AtomicState::new(DISCONNECTED),
+            #[cfg(feature = "tracing")]
+            trace: trace::ChannelTrace::untraced(),
+            message: UnsafeCell::new(MaybeUninit::new(message)),
+            waker: UnsafeCell::new(MaybeUninit::uninit()),
+            #[cfg(any(feature = "std", feature = "async"))]
+            sender_state: AtomicU8::new(0),
+            #[cfg(any(feature = "std", feature = "async"))]
+            sender_waker: UnsafeCell::new(MaybeUninit::uninit()),
         }
     }
 
@@ -1168,10 +2214,9 @@ impl<T> Channel<T> {
     }
 
     #[inline(always)]
-    #[cfg(any(feature = "std", feature = "async"))]
     unsafe fn with_waker_mut<F>(&self, op: F)
     where
-        F: FnOnce(&mut MaybeUninit<ReceiverWaker>),
+        F: FnOnce(&mut MaybeUninit<Option<ReceiverWaker>>),
     {
         #[cfg(oneshot_loom)]
         {
@@ -1207,52 +2252,390 @@ impl<T> Channel<T> {
         self.with_message_mut(|slot| slot.assume_init_drop());
     }
 
-    #[cfg(any(feature = "std", feature = "async"))]
     #[inline(always)]
-    unsafe fn write_waker(&self, waker: ReceiverWaker) {
+    unsafe fn write_waker(&self, waker: Option<ReceiverWaker>) {
+        #[cfg(feature = "async")]
+        self.mark_async_waker(matches!(waker, Some(ReceiverWaker::Task(_))));
         self.with_waker_mut(|slot| slot.as_mut_ptr().write(waker));
     }
 
+    /// Keeps the RECEIVER_ASYNC flag in step with the kind of waker being written.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the receiver.
+    #[cfg(feature = "async")]
+    #[inline(always)]
+    unsafe fn mark_async_waker(&self, is_async: bool) {
+        // ORDERING: Only the receiver changes this flag, so we observe our own earlier write.
+        // The release store of the RECEIVING state after the waker is written publishes it.
+        let was_async = self.sender_state.load(Relaxed) & RECEIVER_ASYNC != 0;
+        if was_async != is_async {
+            self.sender_state.fetch_xor(RECEIVER_ASYNC, Relaxed);
+        }
+    }
+
+    fn public_state(&self) -> ChannelState {
+        // ORDERING: we use acquire ordering to synchronize with the update of the RECEIVER_ASYNC
+        // flag made before the RECEIVING state was stored.
+        match self.state.load(Acquire) {
+            EMPTY => ChannelState::Empty,
+            WRITING => ChannelState::SenderWriting,
+            MESSAGE => ChannelState::Message,
+            #[cfg(feature = "async")]
+            RECEIVING | WRITING_RECEIVING
+                if self.sender_state.load(Relaxed) & RECEIVER_ASYNC != 0 =>
+            {
+                ChannelState::ReceiverWaitingAsync
+            }
+            RECEIVING | WRITING_RECEIVING => ChannelState::ReceiverWaitingSync,
+            UNPARKING => ChannelState::Unparking,
+            DISCONNECTED => ChannelState::Disconnected,
+            CLOSED | REJECTED | CLOSED_DISCONNECTED | WRITING_CLOSED => ChannelState::Closed,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Takes the waker of the receiver, for the sender to unpark it after leaving the UNPARKING
+    /// state. A receiver blocked on the state with a futex stored no waker, and is woken up right
+    /// away instead. It neither sleeps nor frees the channel while UNPARKING, so that wakeup is
+    /// not lost, and the futex is still alive.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the sender, in the UNPARKING state.
+    #[inline(always)]
+    unsafe fn take_waker(&self) -> Option<ReceiverWaker> {
+        #[cfg(oneshot_loom)]
+        let waker = self.waker.with(|ptr| ptr::read(ptr)).assume_init();
+
+        #[cfg(not(oneshot_loom))]
+        let waker = ptr::read(self.waker.get()).assume_init();
+
+        #[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+        if waker.is_none() {
+            futex::wake(&self.state, false);
+        }
+        waker
+    }
+
+    #[inline(always)]
+    unsafe fn drop_waker(&self) {
+        self.with_waker_mut(|slot| slot.assume_init_drop());
+    }
+
+    /// Reserves the channel for the message of `Sender::send_with`, from EMPTY to WRITING or
+    /// from RECEIVING to WRITING_RECEIVING. Returns the state instead if the receiver is gone,
+    /// DISCONNECTED or CLOSED.
+    #[inline]
+    fn start_writing(&self) -> Result<(), State> {
+        // ORDERING: nothing is published or taken over by the reservation. The sender
+        // synchronizes with the receiver when it publishes the message.
+        let mut state = self.state.load(Relaxed);
+        loop {
+            let writing = match state {
+                EMPTY => WRITING,
+                RECEIVING => WRITING_RECEIVING,
+                _ => return Err(state),
+            };
+            match self
+                .state
+                .compare_exchange_weak(state, writing, Relaxed, Relaxed)
+            {
+                Ok(_) => return Ok(()),
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    /// Releases the reservation of `Sender::send_with` without sending, back to EMPTY, RECEIVING
+    /// or CLOSED. Does nothing if the receiver has been dropped in the meantime.
+    fn stop_writing(&self) {
+        // ORDERING: the sender is dropped right after, and synchronizes with the receiver then.
+        let _ = self
+            .state
+            .fetch_update(Relaxed, Relaxed, |state| match state {
+                WRITING => Some(EMPTY),
+                WRITING_RECEIVING => Some(RECEIVING),
+                WRITING_CLOSED => Some(CLOSED),
+                _ => None,
+            });
+    }
+
+    /// Moves the channel into the RECEIVING state after the receiver wrote its waker, from EMPTY,
+    /// or to WRITING_RECEIVING from WRITING. Returns the state instead if the sender has already
+    /// sent the message or was dropped, MESSAGE or DISCONNECTED. The sender did not see the
+    /// waker then, so the receiver must drop it.
+    ///
+    /// ORDERING: release on success so the sender can synchronize with the write of the waker.
+    /// Relaxed on failure, the caller synchronizes with the message if it takes it.
+    #[inline]
+    fn start_receiving(&self) -> Result<(), State> {
+        let mut state = EMPTY;
+        loop {
+            let receiving = match state {
+                EMPTY => RECEIVING,
+                WRITING => WRITING_RECEIVING,
+                _ => return Err(state),
+            };
+            match self
+                .state
+                .compare_exchange(state, receiving, Release, Relaxed)
+            {
+                Ok(_) => return Ok(()),
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    /// Moves the channel out of the RECEIVING state when the receiver stops waiting, back to
+    /// EMPTY, or to WRITING from WRITING_RECEIVING. The receiver owns its waker again on success.
+    /// Returns the state instead if the sender has taken the waker, UNPARKING, MESSAGE or
+    /// DISCONNECTED.
+    ///
+    /// ORDERING: acquire, so a failure synchronizes with the write of the message.
+    #[inline]
+    fn stop_receiving(&self) -> Result<(), State> {
+        let mut state = RECEIVING;
+        loop {
+            let idle = match state {
+                RECEIVING => EMPTY,
+                WRITING_RECEIVING => WRITING,
+                _ => return Err(state),
+            };
+            match self.state.compare_exchange(state, idle, Acquire, Acquire) {
+                Ok(_) => return Ok(()),
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    /// Takes the channel out of the RECEIVING state, left behind by polling the receiver as a
+    /// future or by a `Select`, and drops the stored waker. If the sender is
+    /// currently waking the receiver up, waits until it has moved the channel to MESSAGE or
+    /// DISCONNECTED instead.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the receiver.
+    #[cfg(any(feature = "std", feature = "async"))]
+    unsafe fn reclaim_waker(&self) {
+        // ORDERING: the waker was written by ourselves, and the caller synchronizes with any
+        // message write when it loads the state afterwards.
+        match self.stop_receiving() {
+            // SAFETY: We wrote the waker ourselves, and the sender will not access it after we
+            // left the RECEIVING state.
+            Ok(()) => self.drop_waker(),
+            // The sender is currently waking us up. Wait for it to finish.
+            Err(UNPARKING) => {
+                while self.state.load(Relaxed) == UNPARKING {
+                    hint::spin_loop();
+                }
+            }
+            // The sender has already taken the waker.
+            Err(MESSAGE | DISCONNECTED) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Stores `waker` and moves the channel into the RECEIVING state, so the sender wakes the
+    /// receiver up when it sends or is dropped. Returns false, and drops `waker`, if the sender
+    /// already did either. The message is left in the channel.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the receiver, with no waker stored in the channel and with the
+    /// channel in the EMPTY or WRITING state.
+    #[cfg(feature = "std")]
+    unsafe fn register_waker(&self, waker: ReceiverWaker) -> bool {
+        // SAFETY: we are not yet in the RECEIVING state, meaning that the sender will not
+        // try to access the waker until it sees the state set to RECEIVING below
+        self.write_waker(Some(waker));
+
+        // ORDERING: see `Channel::start_receiving`. On failure we neither take the message nor
+        // free anything, so no synchronization with the sender is needed.
+        match self.start_receiving() {
+            Ok(()) => true,
+            // The sender sent the message or was dropped while we wrote the waker. It did not
+            // see our waker, so we drop it.
+            Err(MESSAGE | DISCONNECTED) => {
+                self.drop_waker();
+                false
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(any(feature = "std", feature = "async"))]
     #[inline(always)]
-    unsafe fn take_waker(&self) -> ReceiverWaker {
+    unsafe fn with_sender_waker_mut<F>(&self, op: F)
+    where
+        F: FnOnce(&mut MaybeUninit<ReceiverWaker>),
+    {
         #[cfg(oneshot_loom)]
         {
-            self.waker.with(|ptr| ptr::read(ptr)).assume_init()
+            self.sender_waker.with_mut(|ptr| op(&mut *ptr))
         }
 
         #[cfg(not(oneshot_loom))]
         {
-            ptr::read(self.waker.get()).assume_init()
+            op(&mut *self.sender_waker.get())
         }
     }
 
     #[cfg(any(feature = "std", feature = "async"))]
     #[inline(always)]
-    unsafe fn drop_waker(&self) {
-        self.with_waker_mut(|slot| slot.assume_init_drop());
+    unsafe fn take_sender_waker(&self) -> ReceiverWaker {
+        #[cfg(oneshot_loom)]
+        {
+            self.sender_waker.with(|ptr| ptr::read(ptr)).assume_init()
+        }
+
+        #[cfg(not(oneshot_loom))]
+        {
+            ptr::read(self.sender_waker.get()).assume_init()
+        }
+    }
+
+    /// Stores `waker` so it is woken up when the receiver sets `flag` in `sender_state`.
+    /// Returns false, and drops `waker`, if `flag` is already set. Any waker stored by an
+    /// earlier call is dropped.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the sender.
+    #[cfg(any(feature = "std", feature = "async"))]
+    unsafe fn register_sender_waker(&self, waker: ReceiverWaker, flag: u8) -> bool {
+        self.unregister_sender_waker();
+
+        // SAFETY: The SENDER_WAKER flag is not set, so the receiver does not touch the waker slot.
+        self.with_sender_waker_mut(|slot| slot.as_mut_ptr().write(waker));
+
+        // ORDERING: we use release ordering on success so the receiver can synchronize with our
+        // write of the waker. Only the receiver modifies the state concurrently with us, and it
+        // only ever sets flags, or toggles RECEIVER_ASYNC. So we retry until either our flag is
+        // set or we have registered.
+        let mut state = self.sender_state.load(Relaxed);
+        loop {
+            if state & flag != 0 {
+                self.with_sender_waker_mut(|slot| slot.assume_init_drop());
+                return false;
+            }
+            match self.sender_state.compare_exchange_weak(
+                state,
+                state | SENDER_WAKER,
+                Release,
+                Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    /// Drops the waker stored by [`Channel::register_sender_waker`], unless the receiver has
+    /// already taken it.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the sender.
+    #[cfg(any(feature = "std", feature = "async"))]
+    unsafe fn unregister_sender_waker(&self) {
+        // ORDERING: The waker was written by ourselves, so no synchronization is needed to drop it.
+        let mut state = self.sender_state.load(Relaxed);
+        while state & SENDER_WAKER != 0 {
+            match self.sender_state.compare_exchange_weak(
+                state,
+                state & !SENDER_WAKER,
+                Relaxed,
+                Relaxed,
+            ) {
+                Ok(_) => {
+                    // SAFETY: we cleared the SENDER_WAKER flag, so we own the stored waker.
+                    self.with_sender_waker_mut(|slot| slot.assume_init_drop());
+                    return;
+                }
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    /// Sets `flag` in `sender_state` and takes the sender's waker, if one is registered.
+    /// The caller is responsible for waking the sender up with the returned waker.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the receiver.
+    #[cfg(any(feature = "std", feature = "async"))]
+    unsafe fn notify_sender(&self, flag: u8) -> Option<ReceiverWaker> {
+        // ORDERING: we use acquire ordering to synchronize with the write of the waker.
+        let previous = self
+            .sender_state
+            .fetch_update(AcqRel, Acquire, |state| {
+                Some((state | flag) & !SENDER_WAKER)
+            })
+            .unwrap();
+        if previous & SENDER_WAKER != 0 {
+            // SAFETY: we cleared the SENDER_WAKER flag, so we own the stored waker.
+            Some(self.take_sender_waker())
+        } else {
+            None
+        }
+    }
+
+    /// Lets the sender know that the receiver has started waiting for the message, unless
+    /// that has already been done.
+    ///
+    /// Called by a blocking receive in the EMPTY or WRITING state, before it writes its waker. Lets a sender
+    /// waiting for demand know that we are waiting, then gives the parker a chance to busy wait
+    /// for the message. Updates `state` if it changed while spinning.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the receiver.
+    #[inline]
+    unsafe fn spin_while_empty(&self, parker: &impl Park, state: &mut State) {
+        // Let a sender waiting in `Sender::wait_for_demand` know that we are waiting.
+        #[cfg(any(feature = "std", feature = "async"))]
+        self.notify_demand();
+
+        parker.spin(|| {
+            // ORDERING: synchronize with the write of the message
+            *state = self.state.load(Acquire);
+            !matches!(*state, EMPTY | WRITING)
+        });
+    }
+
+    /// # Safety
+    ///
+    /// Must only be called by the receiver.
+    #[cfg(any(feature = "std", feature = "async"))]
+    #[inline]
+    unsafe fn notify_demand(&self) {
+        // ORDERING: Only the receiver sets this flag, so we will observe our own earlier write.
+        if self.sender_state.load(Relaxed) & RECEIVER_DEMAND == 0 {
+            if let Some(waker) = self.notify_sender(RECEIVER_DEMAND) {
+                waker.unpark();
+            }
+        }
     }
 
     /// # Safety
     ///
     /// * `Channel::waker` must not have a waker stored in it when calling this method.
-    /// * Channel state must not be RECEIVING or UNPARKING when calling this method.
+    /// * Channel state must be EMPTY or WRITING when calling this method.
     #[cfg(feature = "async")]
     unsafe fn write_async_waker(&self, cx: &mut task::Context<'_>) -> Poll<Result<T, RecvError>> {
         // Write our thread instance to the channel.
         // SAFETY: we are not yet in the RECEIVING state, meaning that the sender will not
         // try to access the waker until it sees the state set to RECEIVING below
-        self.write_waker(ReceiverWaker::task_waker(cx));
+        self.write_waker(Some(ReceiverWaker::task_waker(cx)));
 
-        // ORDERING: we use release ordering on success so the sender can synchronize with
-        // our write of the waker. We use relaxed ordering on failure since the sender does
-        // not need to synchronize with our write and the individual match arms handle any
+        // ORDERING: see `Channel::start_receiving`. The individual match arms handle any
         // additional synchronization
-        match self
-            .state
-            .compare_exchange(EMPTY, RECEIVING, Release, Relaxed)
-        {
+        match self.start_receiving() {
             // We stored our waker, now we return and let the sender wake us up
-            Ok(_) => Poll::Pending,
+            Ok(()) => Poll::Pending,
             // The sender sent the message while we prepared to park.
             // We take the message and mark the channel disconnected.
             Err(MESSAGE) => {
@@ -1292,9 +2675,8 @@ enum ReceiverWaker {
     /// The receiver is waiting asynchronously. Its task can be woken up with this `Waker`.
     #[cfg(feature = "async")]
     Task(task::Waker),
-    /// A little hack to not make this enum an uninhibitable type when no features are enabled.
-    #[cfg(not(any(feature = "async", feature = "std")))]
-    _Uninhabited,
+    /// The receiver is waiting synchronously, blocked by a user provided `Parker`.
+    Parker(parker::RawUnparker),
 }
 
 impl ReceiverWaker {
@@ -1314,8 +2696,7 @@ impl ReceiverWaker {
             ReceiverWaker::Thread(thread) => thread.unpark(),
             #[cfg(feature = "async")]
             ReceiverWaker::Task(waker) => waker.wake(),
-            #[cfg(not(any(feature = "async", feature = "std")))]
-            ReceiverWaker::_Uninhabited => unreachable!(),
+            ReceiverWaker::Parker(unparker) => unparker.unpark(),
         }
     }
 }
@@ -1325,19 +2706,43 @@ impl ReceiverWaker {
 #[ignore = "Unstable test. Different Rust versions have different sizes for Thread"]
 fn receiver_waker_size() {
     let expected: usize = match (cfg!(feature = "std"), cfg!(feature = "async")) {
-        (false, false) => 0,
-        (false, true) => 16,
-        (true, false) => 16,
+        (false, false) => 16,
+        (false, true) => 24,
+        (true, false) => 24,
         (true, true) => 24,
     };
     assert_eq!(mem::size_of::<ReceiverWaker>(), expected);
 }
 
-#[cfg(all(feature = "std", feature = "async"))]
-const RECEIVER_USED_SYNC_AND_ASYNC_ERROR: &str =
-    "Invalid to call a blocking receive method on oneshot::Receiver after it has been polled";
+/// The memory allocated for each channel. Holds the allocator next to the channel itself, so it
+/// is available to whichever endpoint ends up freeing the channel.
+#[repr(C)]
+struct Allocation<T, A> {
+    // Must be the first field. The endpoints point to the channel, and `dealloc` casts that
+    // pointer back to the whole allocation.
+    channel: Channel<T>,
+    alloc: A,
+}
 
+/// The layout of the memory allocated for a channel sending `T` and allocated with `A`.
+#[inline]
+pub(crate) const fn allocation_layout<T, A>() -> Layout {
+    Layout::new::<Allocation<T, A>>()
+}
+
+/// Drops the channel and frees its memory with the allocator stored next to it.
+///
+/// # Safety
+///
+/// `channel` must have been allocated by `channel_in` with allocator type `A`, and must not be
+/// used after this call.
 #[inline]
-pub(crate) unsafe fn dealloc<T>(channel: NonNull<Channel<T>>) {
-    drop(Box::from_raw(channel.as_ptr()))
+pub(crate) unsafe fn dealloc<T, A: Allocator>(channel: NonNull<Channel<T>>) {
+    let allocation = channel.cast::<Allocation<T, A>>().as_ptr();
+    ptr::drop_in_place(ptr::addr_of_mut!((*allocation).channel));
+    let alloc = ptr::read(ptr::addr_of!((*allocation).alloc));
+    alloc.deallocate(
+        NonNull::new_unchecked(allocation).cast(),
+        allocation_layout::<T, A>(),
+    );
 }
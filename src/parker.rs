@@ -0,0 +1,574 @@
+//! Blocking receive on any platform, via user provided thread primitives.
+//!
+//! The blocking receive methods on [`Receiver`](crate::Receiver), like `Receiver::recv`, park
+//! the current thread with the standard library. Without the `std` feature, or on platforms with their own threading, implement
+//! [`Parker`] instead and use [`Receiver::recv_with`](crate::Receiver::recv_with) and its
+//! siblings. For deadlines, also implement [`TimedParker`], which brings its own clock.
+
+use crate::ReceiverWaker;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop, MaybeUninit};
+use core::ptr;
+
+use alloc::boxed::Box;
+
+#[cfg(not(oneshot_loom))]
+use core::hint;
+#[cfg(oneshot_loom)]
+use loom::hint;
+
+/// Blocks the current thread until it is unparked by an [`Unparker`] created by the parker.
+///
+/// Used by [`Receiver::recv_with`](crate::Receiver::recv_with) and its siblings to wait for the
+/// message.
+pub trait Parker {
+    /// The handle the sender uses to wake the receiving thread up.
+    type Unparker: Unparker;
+
+    /// Returns a handle that unparks the thread calling [`Parker::park`] on this parker.
+    fn unparker(&self) -> Self::Unparker;
+
+    /// Blocks the current thread until it is unparked by an unparker from this parker. If the
+    /// thread was unparked before this call, it must return immediately.
+    ///
+    /// It is allowed to return spuriously, without being unparked. The receiver checks the
+    /// channel state and parks again if needed.
+    fn park(&self);
+
+    /// Called once per receive, before the receiver hands out an unparker and parks. May busy
+    /// wait for the message by calling `ready` until it returns true, which avoids the cost of
+    /// parking and unparking when the message is about to arrive. Returns true if `ready` did.
+    ///
+    /// The default implementation does not spin. See `WaitStrategy` for a parker that does.
+    #[inline]
+    fn spin<F: FnMut() -> bool>(&self, ready: F) -> bool {
+        let _ = ready;
+        false
+    }
+}
+
+/// Wakes up a thread blocked in [`Parker::park`]. Created by [`Parker::unparker`].
+///
+/// Unparkers fitting in a pointer are stored inline in the channel. Larger ones are boxed.
+pub trait Unparker: Send + 'static {
+    /// Unparks the thread. If the thread is not parked yet, its next call to [`Parker::park`]
+    /// must return immediately.
+    fn unpark(self);
+}
+
+/// A [`Parker`] with its own clock, allowing to block with a deadline. Used by
+/// [`Receiver::recv_deadline_with`](crate::Receiver::recv_deadline_with).
+pub trait TimedParker: Parker {
+    /// A point in time on the clock of this parker.
+    type Instant: Ord;
+
+    /// Returns the current time.
+    fn now(&self) -> Self::Instant;
+
+    /// Like [`Parker::park`], but returns no later than around `deadline`.
+    fn park_until(&self, deadline: &Self::Instant);
+}
+
+/// A [`Parker`] that does not block, but busy waits with a spin loop hint.
+///
+/// Works on any platform, but keeps the waiting thread busy.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SpinParker;
+
+/// The [`Unparker`] of [`SpinParker`]. Does nothing, since the spinning thread notices the
+/// message on its own.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SpinUnparker;
+
+impl Parker for SpinParker {
+    type Unparker = SpinUnparker;
+
+    #[inline]
+    fn unparker(&self) -> SpinUnparker {
+        SpinUnparker
+    }
+
+    #[inline]
+    fn park(&self) {
+        hint::spin_loop();
+    }
+}
+
+impl Unparker for SpinUnparker {
+    #[inline]
+    fn unpark(self) {}
+}
+
+/// A [`Parker`] blocking with the standard library, like `Receiver::recv`, that can busy wait
+/// for the message before parking. Parking and unparking a thread takes a few microseconds, so
+/// when the message usually arrives sooner than that, spinning gives a lower latency.
+///
+/// Pass it to the receives that should spin, instead of calling `Receiver::recv`,
+/// `Receiver::recv_ref` or `Receiver::recv_deadline`:
+/// [`Receiver::recv_with`](crate::Receiver::recv_with),
+/// [`Receiver::recv_ref_with`](crate::Receiver::recv_ref_with) and
+/// [`Receiver::recv_deadline_with`](crate::Receiver::recv_deadline_with). The plain receive
+/// methods never spin, so other code in the process is not affected by the choice:
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// use oneshot::parker::WaitStrategy;
+///
+/// static STRATEGY: WaitStrategy = WaitStrategy::adaptive(1000);
+///
+/// let (sender, receiver) = oneshot::channel();
+/// std::thread::spawn(move || sender.send(1).unwrap());
+/// assert_eq!(receiver.recv_with(&STRATEGY), Ok(1));
+///
+/// let (sender, receiver) = oneshot::channel();
+/// std::thread::spawn(move || sender.send(2).unwrap());
+/// assert_eq!(receiver.recv_ref_with(&STRATEGY), Ok(2));
+/// # }
+/// ```
+///
+/// Spinning only pays off when the sender runs on another CPU core at the same time. On a
+/// machine with a single core the strategy always parks right away. The spinning is not cut short
+/// by the deadline of `recv_deadline_with`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct WaitStrategy {
+    max_spins: u32,
+    adaptive: bool,
+    /// The moving average of how many spins previous waits needed, counting waits that ended up
+    /// parking as zero. Only used when adaptive. A plain atomic, also under loom, since it is
+    /// only a heuristic and not part of the channel synchronization.
+    average_spins: core::sync::atomic::AtomicU32,
+}
+
+#[cfg(feature = "std")]
+impl WaitStrategy {
+    /// The fewest spins an adaptive strategy makes, so it keeps noticing when messages start
+    /// arriving quickly again.
+    const MIN_ADAPTIVE_SPINS: u32 = 16;
+
+    /// Parks right away, without spinning, like `Receiver::recv` and its siblings.
+    pub const fn no_spin() -> Self {
+        Self::spin_then_park(0)
+    }
+
+    /// Spins up to `spins` times before parking.
+    pub const fn spin_then_park(spins: u32) -> Self {
+        Self {
+            max_spins: spins,
+            adaptive: false,
+            average_spins: core::sync::atomic::AtomicU32::new(0),
+        }
+    }
+
+    /// Spins up to about twice as many times as previous waits with this strategy needed, but
+    /// never more than `max_spins` times, before parking. Starts out spinning `max_spins` times.
+    /// Waits that end up parking lower the number of spins, so the strategy stops spinning
+    /// where that does not pay off, and waits that succeed while spinning raise it again. Share
+    /// one strategy between receives with similar latencies, for example in a `static`.
+    pub const fn adaptive(max_spins: u32) -> Self {
+        Self {
+            max_spins,
+            adaptive: true,
+            average_spins: core::sync::atomic::AtomicU32::new(max_spins / 2),
+        }
+    }
+
+    /// Returns how many times the next wait should spin at most.
+    fn spin_limit(&self) -> u32 {
+        if !self.adaptive {
+            return self.max_spins;
+        }
+        let average = self
+            .average_spins
+            .load(core::sync::atomic::Ordering::Relaxed);
+        average
+            .saturating_mul(2)
+            .saturating_add(Self::MIN_ADAPTIVE_SPINS)
+            .min(self.max_spins)
+    }
+
+    /// Moves the average an eighth of the way towards the spins the last wait needed, or towards
+    /// zero if it parked.
+    fn record_spins(&self, spins: u32) {
+        use core::sync::atomic::Ordering::Relaxed;
+
+        // Concurrent waits might overwrite each others updates. That is fine for an estimate.
+        let average = self.average_spins.load(Relaxed);
+        let average = if spins >= average {
+            average + (spins - average) / 8
+        } else {
+            average - (average - spins) / 8
+        };
+        self.average_spins.store(average, Relaxed);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Parker for WaitStrategy {
+    type Unparker = ThreadUnparker;
+
+    #[inline]
+    fn unparker(&self) -> ThreadUnparker {
+        ThreadUnparker(crate::thread::current())
+    }
+
+    #[inline]
+    fn park(&self) {
+        crate::thread::park()
+    }
+
+    #[inline]
+    fn spin<F: FnMut() -> bool>(&self, mut ready: F) -> bool {
+        let limit = self.spin_limit();
+        if limit == 0 || !multiple_cores() {
+            return false;
+        }
+        let mut spins = 0;
+        let ready = loop {
+            if ready() {
+                break true;
+            }
+            if spins == limit {
+                break false;
+            }
+            hint::spin_loop();
+            spins += 1;
+        };
+        if self.adaptive {
+            // Spinning did not pay off if we park, so such waits pull the average down.
+            self.record_spins(if ready { spins } else { 0 });
+        }
+        ready
+    }
+}
+
+#[cfg(feature = "std")]
+impl TimedParker for WaitStrategy {
+    type Instant = std::time::Instant;
+
+    #[inline]
+    fn now(&self) -> std::time::Instant {
+        std::time::Instant::now()
+    }
+
+    #[inline]
+    fn park_until(&self, deadline: &std::time::Instant) {
+        crate::thread::park_timeout(deadline.saturating_duration_since(std::time::Instant::now()))
+    }
+}
+
+/// Returns whether the other thread can make progress while we spin.
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+fn multiple_cores() -> bool {
+    use core::sync::atomic::{AtomicU8, Ordering::Relaxed};
+
+    const UNKNOWN: u8 = 0;
+    const SINGLE: u8 = 1;
+    const MULTIPLE: u8 = 2;
+    static CORES: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+    match CORES.load(Relaxed) {
+        UNKNOWN => {
+            let multiple = std::thread::available_parallelism().map_or(true, |n| n.get() > 1);
+            CORES.store(if multiple { MULTIPLE } else { SINGLE }, Relaxed);
+            multiple
+        }
+        cores => cores == MULTIPLE,
+    }
+}
+
+/// loom runs all threads on one core, but the spinning is still worth checking.
+#[cfg(all(feature = "std", oneshot_loom))]
+fn multiple_cores() -> bool {
+    true
+}
+
+/// The [`Unparker`] of [`WaitStrategy`]. Unparks the receiving thread with the standard library.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ThreadUnparker(crate::thread::Thread);
+
+#[cfg(feature = "std")]
+impl Unparker for ThreadUnparker {
+    #[inline]
+    fn unpark(self) {
+        self.0.unpark()
+    }
+}
+
+/// How a blocking receive method parks the current thread. Implemented by the standard library
+/// thread parking when `std` is enabled, and by any [`Parker`].
+pub(crate) trait Park {
+    /// Returns the waker the sender uses to unpark this thread, or `None` if the thread blocks on
+    /// the channel state with a futex, which the sender wakes directly.
+    fn receiver_waker(&self) -> Option<ReceiverWaker>;
+
+    fn park(&self);
+
+    /// Busy waits until `ready` returns true, or gives up. See [`Parker::spin`].
+    #[inline]
+    fn spin(&self, ready: impl FnMut() -> bool) -> bool {
+        let _ = ready;
+        false
+    }
+}
+
+/// A [`Park`] that can also park until a deadline.
+pub(crate) trait ParkDeadline: Park {
+    type Instant;
+
+    /// Parks the current thread until `deadline` at most. Returns false without parking if the
+    /// deadline has already been reached.
+    fn park_until(&self, deadline: &Self::Instant) -> bool;
+}
+
+/// Parks the current thread with the standard library.
+#[cfg(all(
+    feature = "std",
+    not(all(feature = "futex", target_os = "linux", not(oneshot_loom)))
+))]
+pub(crate) struct ThreadPark;
+
+#[cfg(all(
+    feature = "std",
+    not(all(feature = "futex", target_os = "linux", not(oneshot_loom)))
+))]
+impl Park for ThreadPark {
+    #[inline]
+    fn receiver_waker(&self) -> Option<ReceiverWaker> {
+        Some(ReceiverWaker::current_thread())
+    }
+
+    #[inline]
+    fn park(&self) {
+        crate::thread::park()
+    }
+}
+
+#[cfg(all(
+    feature = "std",
+    not(all(feature = "futex", target_os = "linux", not(oneshot_loom)))
+))]
+impl ParkDeadline for ThreadPark {
+    type Instant = std::time::Instant;
+
+    #[inline]
+    fn park_until(&self, deadline: &Self::Instant) -> bool {
+        match deadline.checked_duration_since(std::time::Instant::now()) {
+            Some(timeout) => {
+                crate::thread::park_timeout(timeout);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Blocks the current thread on the state of the channel it receives on, with a futex. The
+/// sender stores no waker for it, and wakes the futex once it has left the waiting state.
+#[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+pub(crate) struct FutexPark(pub *const core::sync::atomic::AtomicU32);
+
+#[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+impl FutexPark {
+    fn wait(&self, timeout: Option<std::time::Duration>) {
+        // SAFETY: the receiver only parks while the channel is alive.
+        let state = unsafe { &*self.0 };
+        // ORDERING: the receive methods synchronize with the sender after waking up.
+        let current = state.load(core::sync::atomic::Ordering::Relaxed);
+        match current {
+            // Sleep as long as the sender has not started waking us up. It wakes the futex after
+            // leaving these states, so the kernel either sees the new state or wakes us.
+            crate::states::RECEIVING | crate::states::WRITING_RECEIVING => {
+                crate::futex::wait(state, current, timeout, false)
+            }
+            // The sender is about to store the MESSAGE or DISCONNECTED state. It does not wake
+            // us again, so we spin instead of sleeping.
+            crate::states::UNPARKING => core::hint::spin_loop(),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+impl Park for FutexPark {
+    #[inline]
+    fn receiver_waker(&self) -> Option<ReceiverWaker> {
+        None
+    }
+
+    #[inline]
+    fn park(&self) {
+        self.wait(None)
+    }
+}
+
+#[cfg(all(feature = "futex", target_os = "linux", not(oneshot_loom)))]
+impl ParkDeadline for FutexPark {
+    type Instant = std::time::Instant;
+
+    #[inline]
+    fn park_until(&self, deadline: &Self::Instant) -> bool {
+        match deadline.checked_duration_since(std::time::Instant::now()) {
+            Some(timeout) => {
+                self.wait(Some(timeout));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Parks the current thread with a user provided [`Parker`].
+pub(crate) struct UserPark<'a, P>(pub &'a P);
+
+impl<P: Parker> Park for UserPark<'_, P> {
+    #[inline]
+    fn receiver_waker(&self) -> Option<ReceiverWaker> {
+        Some(ReceiverWaker::Parker(RawUnparker::new(self.0.unparker())))
+    }
+
+    #[inline]
+    fn park(&self) {
+        self.0.park()
+    }
+
+    #[inline]
+    fn spin(&self, ready: impl FnMut() -> bool) -> bool {
+        self.0.spin(ready)
+    }
+}
+
+impl<P: TimedParker> ParkDeadline for UserPark<'_, P> {
+    type Instant = P::Instant;
+
+    #[inline]
+    fn park_until(&self, deadline: &Self::Instant) -> bool {
+        if self.0.now() >= *deadline {
+            return false;
+        }
+        self.0.park_until(deadline);
+        true
+    }
+}
+
+/// A type erased [`Unparker`]. Stores the unparker inline if it fits in a pointer, otherwise
+/// boxed.
+pub(crate) struct RawUnparker {
+    data: MaybeUninit<*mut ()>,
+    vtable: &'static UnparkerVTable,
+}
+
+struct UnparkerVTable {
+    unpark: unsafe fn(MaybeUninit<*mut ()>),
+    drop: unsafe fn(MaybeUninit<*mut ()>),
+}
+
+impl RawUnparker {
+    pub fn new<U: Unparker>(unparker: U) -> Self {
+        let mut data = MaybeUninit::<*mut ()>::uninit();
+        if Inline::<U>::FITS {
+            // SAFETY: we just checked that `U` fits in `data`.
+            unsafe { data.as_mut_ptr().cast::<U>().write(unparker) };
+            Self {
+                data,
+                vtable: &Inline::<U>::VTABLE,
+            }
+        } else {
+            data.write(Box::into_raw(Box::new(unparker)).cast::<()>());
+            Self {
+                data,
+                vtable: &Boxed::<U>::VTABLE,
+            }
+        }
+    }
+
+    pub fn unpark(self) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: the data was written by `new` with the same unparker type as the vtable, and is
+        // consumed only once since we do not run our Drop implementation.
+        unsafe { (this.vtable.unpark)(this.data) }
+    }
+}
+
+impl Drop for RawUnparker {
+    fn drop(&mut self) {
+        // SAFETY: the data was written by `new` with the same unparker type as the vtable.
+        unsafe { (self.vtable.drop)(self.data) }
+    }
+}
+
+impl fmt::Debug for RawUnparker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawUnparker").finish_non_exhaustive()
+    }
+}
+
+struct Inline<U>(PhantomData<U>);
+
+impl<U: Unparker> Inline<U> {
+    const FITS: bool = mem::size_of::<U>() <= mem::size_of::<*mut ()>()
+        && mem::align_of::<U>() <= mem::align_of::<*mut ()>();
+
+    const VTABLE: UnparkerVTable = UnparkerVTable {
+        unpark: Self::unpark,
+        drop: Self::drop,
+    };
+
+    unsafe fn unpark(data: MaybeUninit<*mut ()>) {
+        ptr::read(data.as_ptr().cast::<U>()).unpark()
+    }
+
+    unsafe fn drop(data: MaybeUninit<*mut ()>) {
+        drop(ptr::read(data.as_ptr().cast::<U>()))
+    }
+}
+
+struct Boxed<U>(PhantomData<U>);
+
+impl<U: Unparker> Boxed<U> {
+    const VTABLE: UnparkerVTable = UnparkerVTable {
+        unpark: Self::unpark,
+        drop: Self::drop,
+    };
+
+    unsafe fn unpark(data: MaybeUninit<*mut ()>) {
+        Box::from_raw(data.assume_init().cast::<U>()).unpark()
+    }
+
+    unsafe fn drop(data: MaybeUninit<*mut ()>) {
+        drop(Box::from_raw(data.assume_init().cast::<U>()))
+    }
+}
+
+#[cfg(all(test, feature = "std", not(oneshot_loom)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_spin_limit_follows_waits() {
+        if !multiple_cores() {
+            return;
+        }
+        let strategy = WaitStrategy::adaptive(1000);
+        assert_eq!(strategy.spin_limit(), 1000);
+
+        // Waits that park make the strategy spin less, down to the minimum.
+        for _ in 0..100 {
+            assert!(!strategy.spin(|| false));
+        }
+        assert_eq!(strategy.spin_limit(), WaitStrategy::MIN_ADAPTIVE_SPINS);
+
+        // Waits that succeed near the limit make it spin longer again.
+        for _ in 0..100 {
+            let limit = strategy.spin_limit();
+            let mut spins = 0;
+            assert!(strategy.spin(|| {
+                spins += 1;
+                spins > limit
+            }));
+        }
+        assert_eq!(strategy.spin_limit(), 1000);
+    }
+}
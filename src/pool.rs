@@ -0,0 +1,243 @@
+use crate::{allocation_layout, channel_at, AllocError, Allocator, Global, Receiver, Sender};
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
+
+#[cfg(not(oneshot_loom))]
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering::*};
+#[cfg(oneshot_loom)]
+use loom::sync::atomic::{AtomicPtr, AtomicUsize, Ordering::*};
+
+/// A pool of channel allocations. Creates channels sending `T` that return their memory to the
+/// pool when both endpoints are gone, instead of freeing it. Later channels created from the same
+/// pool reuse that memory, avoiding the cost of the global allocator in the common case.
+///
+/// The pool keeps every allocation it has handed out for reuse, so its memory usage is bounded by
+/// the largest number of channels from it alive at the same time. All memory is freed when the
+/// pool and all its channels have been dropped.
+///
+/// The pool itself can be moved between threads but not shared. Create one pool per thread to
+/// create channels from multiple threads. The channels can be used and dropped on any thread.
+pub struct ChannelPool<T> {
+    freelist: NonNull<Freelist>,
+    /// Allocations taken out of the shared freelist, only accessed by the pool itself.
+    cached: Cell<*mut FreeBlock>,
+    /// The number of allocations made by the pool.
+    allocated: Cell<usize>,
+    _message: PhantomData<fn(T) -> T>,
+}
+
+// SAFETY: The pool only holds pointers to unused allocations, which are not shared with anyone,
+// and to the freelist, which is thread safe.
+unsafe impl<T> Send for ChannelPool<T> {}
+
+impl<T> ChannelPool<T> {
+    /// Creates a new, empty, pool.
+    pub fn new() -> Self {
+        let layout = Layout::new::<Freelist>();
+        let freelist = match Global.allocate(layout) {
+            Ok(ptr) => ptr.cast::<Freelist>(),
+            Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+        };
+        // SAFETY: the memory was just allocated for a `Freelist`.
+        unsafe {
+            freelist.as_ptr().write(Freelist {
+                head: AtomicPtr::new(ptr::null_mut()),
+                outstanding: AtomicUsize::new(0),
+                layout: allocation_layout::<T, PoolAllocator>(),
+            })
+        };
+        Self {
+            freelist,
+            cached: Cell::new(ptr::null_mut()),
+            allocated: Cell::new(0),
+            _message: PhantomData,
+        }
+    }
+
+    /// Creates a new oneshot channel and returns the two endpoints, [`Sender`] and [`Receiver`].
+    /// Works like [`channel`](crate::channel), but reuses the memory of a previous channel from
+    /// this pool if there is one.
+    pub fn channel(&self) -> (Sender<T, PoolAllocator>, Receiver<T, PoolAllocator>) {
+        let alloc = PoolAllocator {
+            freelist: self.freelist,
+        };
+        let allocation = match NonNull::new(self.take_cached()) {
+            Some(block) => block.cast(),
+            None => {
+                let layout = allocation_layout::<T, PoolAllocator>();
+                let allocation = match Global.allocate(layout) {
+                    Ok(allocation) => allocation,
+                    Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+                };
+                self.allocated.set(self.allocated.get() + 1);
+                allocation
+            }
+        };
+        // SAFETY: the allocation is either an unused block from the freelist, or freshly
+        // allocated, in both cases with the layout of a channel sending `T`. The pool allocator
+        // takes care of both returning it to the freelist and freeing it.
+        unsafe { channel_at(allocation, alloc) }
+    }
+
+    /// Pops an unused allocation from the cache, refilling the cache from the shared freelist if
+    /// it is empty. Returns null if there are no unused allocations.
+    fn take_cached(&self) -> *mut FreeBlock {
+        let mut block = self.cached.get();
+        if block.is_null() {
+            // SAFETY: the freelist is alive as long as the pool is.
+            let freelist = unsafe { self.freelist.as_ref() };
+            // ORDERING: synchronize with the writes of the `next` pointers in `push`.
+            block = freelist.head.swap(ptr::null_mut(), Acquire);
+        }
+        if !block.is_null() {
+            // SAFETY: the block is unused and owned by us, and was initialized as a free block
+            // when it was pushed to the freelist.
+            self.cached.set(unsafe { (*block).next });
+        }
+        block
+    }
+}
+
+impl<T> Default for ChannelPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ChannelPool<T> {
+    fn drop(&mut self) {
+        // SAFETY: the freelist is alive as long as the pool is.
+        let freelist = unsafe { self.freelist.as_ref() };
+
+        // Close the freelist so allocations of channels still alive are freed instead of returned.
+        // ORDERING: synchronize with the writes of the `next` pointers in `push`.
+        let returned = freelist.head.swap(Freelist::CLOSED, Acquire);
+        let mut unused = 0;
+        for list in [self.cached.get(), returned] {
+            let mut block = list;
+            while !block.is_null() {
+                // SAFETY: all blocks in the lists are unused and owned by us.
+                unsafe {
+                    let next = (*block).next;
+                    Global.deallocate(NonNull::new_unchecked(block).cast(), freelist.layout);
+                    block = next;
+                }
+                unused += 1;
+            }
+        }
+
+        // Allocations not in the freelist belong to channels that are still alive. The last one
+        // of them to be freed also frees the freelist. They might already have been freed, and
+        // decremented the counter below zero, in which case this brings it back up to zero.
+        let outstanding = self.allocated.get() - unused;
+        // ORDERING: we use acquire-release ordering so whoever frees the freelist synchronizes
+        // with all other accesses to it.
+        let previous = freelist.outstanding.fetch_add(outstanding, AcqRel);
+        if previous.wrapping_add(outstanding) == 0 {
+            // SAFETY: all channels from this pool are gone.
+            unsafe { Freelist::dealloc(self.freelist) };
+        }
+    }
+}
+
+impl<T> fmt::Debug for ChannelPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelPool").finish_non_exhaustive()
+    }
+}
+
+/// The [`Allocator`] of channels created by a [`ChannelPool`]. Returns the memory of the channel
+/// to the pool it came from.
+pub struct PoolAllocator {
+    freelist: NonNull<Freelist>,
+}
+
+// SAFETY: the freelist is thread safe.
+unsafe impl Send for PoolAllocator {}
+unsafe impl Sync for PoolAllocator {}
+
+unsafe impl Allocator for PoolAllocator {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        // SAFETY: the freelist is alive as long as the pool or any channel from it is.
+        let freelist = self.freelist.as_ref();
+        debug_assert_eq!(layout, freelist.layout);
+        if !freelist.push(ptr.cast()) {
+            Global.deallocate(ptr, layout);
+            // The pool is gone. Free the freelist if this was the last channel from the pool.
+            // ORDERING: see `ChannelPool::drop`.
+            if freelist.outstanding.fetch_sub(1, AcqRel) == 1 {
+                Freelist::dealloc(self.freelist);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PoolAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolAllocator").finish_non_exhaustive()
+    }
+}
+
+/// Unused channel allocations, shared between a pool and the channels created from it.
+/// A lock-free stack where any thread can push, but only the pool can take the entire stack
+/// at once. Not popping single entries avoids the ABA problem.
+struct Freelist {
+    head: AtomicPtr<FreeBlock>,
+    /// Only used after the pool is dropped. The number of channels from the pool still alive.
+    outstanding: AtomicUsize,
+    /// The layout of every allocation in the list.
+    layout: Layout,
+}
+
+/// What an unused channel allocation in the freelist holds. A channel allocation always fits at
+/// least one pointer, since it contains the pointer to the freelist in the `PoolAllocator`.
+struct FreeBlock {
+    next: *mut FreeBlock,
+}
+
+impl Freelist {
+    /// The value of `head` after the pool has been dropped.
+    const CLOSED: *mut FreeBlock = NonNull::<FreeBlock>::dangling().as_ptr();
+
+    /// Pushes the unused allocation to the freelist. Returns false if the pool has been dropped,
+    /// in which case the caller has to free the allocation.
+    ///
+    /// # Safety
+    ///
+    /// The allocation must be unused, owned by the caller, and have the layout of the list.
+    unsafe fn push(&self, block: NonNull<FreeBlock>) -> bool {
+        let mut head = self.head.load(Relaxed);
+        loop {
+            if head == Self::CLOSED {
+                return false;
+            }
+            block.as_ptr().write(FreeBlock { next: head });
+            // ORDERING: we use release ordering so the pool can synchronize with our write of the
+            // `next` pointer, and any accesses to the channel before it was freed.
+            match self
+                .head
+                .compare_exchange_weak(head, block.as_ptr(), Release, Relaxed)
+            {
+                Ok(_) => return true,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The pool and all channels from it must be gone.
+    unsafe fn dealloc(freelist: NonNull<Freelist>) {
+        ptr::drop_in_place(freelist.as_ptr());
+        Global.deallocate(freelist.cast(), Layout::new::<Freelist>());
+    }
+}
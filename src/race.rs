@@ -0,0 +1,239 @@
+use crate::{
+    channel_at, AllocError, Allocation, Allocator, Channel, Global, Receiver, SendError, Sender,
+};
+use core::alloc::Layout;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr::{self, NonNull};
+
+#[cfg(not(oneshot_loom))]
+use core::sync::atomic::{AtomicU32, Ordering::*};
+#[cfg(oneshot_loom)]
+use loom::sync::atomic::{AtomicU32, Ordering::*};
+
+/// The number of `RaceSender`s alive, in the low bits of `Header::senders`.
+const SENDERS: u32 = (1 << 30) - 1;
+/// Set in `Header::senders` by the `RaceSender` that won the race, in the same operation that
+/// removes it from the count. From then on the winner acts as the sender of the channel.
+const WON: u32 = 1 << 30;
+/// Set in `Header::senders` by `RaceAllocator::deallocate` once the channel is freed. The
+/// allocation is released by whichever of that and the last `RaceSender` comes second.
+const FREED: u32 = 1 << 31;
+
+/// Creates a new oneshot channel with any number of senders, and returns the two endpoints,
+/// [`RaceSender`] and [`Receiver`].
+///
+/// The [`RaceSender`] can be cloned. The first clone to send wins, and its message is the one
+/// the receiver gets. All later sends fail and hand their message back in the
+/// [`SendError`]. The receiver only sees the channel as disconnected once all senders are
+/// dropped without any of them sending.
+///
+/// Calls [`handle_alloc_error`](alloc::alloc::handle_alloc_error) if the allocation fails.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// let (sender, receiver) = oneshot::race();
+/// let backup = sender.clone();
+///
+/// assert!(sender.send("fast reply").is_ok());
+/// assert_eq!(backup.send("slow reply").unwrap_err().into_inner(), "slow reply");
+/// assert_eq!(receiver.try_recv(), Ok("fast reply"));
+/// # }
+/// ```
+pub fn race<T>() -> (RaceSender<T>, Receiver<T, RaceAllocator>) {
+    let (layout, offset) = Layout::new::<Header>()
+        .extend(Layout::new::<Allocation<T, RaceAllocator>>())
+        .expect("capacity overflow");
+    let layout = layout.pad_to_align();
+    let header = match Global.allocate(layout) {
+        Ok(ptr) => ptr.cast::<Header>(),
+        Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+    };
+    // SAFETY: the memory was just allocated, and starts with room for the header.
+    unsafe {
+        header.as_ptr().write(Header {
+            senders: AtomicU32::new(1),
+            layout,
+        })
+    };
+    // SAFETY: the channel allocation starts `offset` bytes into the race allocation, is unused
+    // and has the layout of a channel sending `T`. The race allocator keeps the memory until the
+    // last race sender is gone too.
+    let (sender, receiver) = unsafe {
+        let allocation = NonNull::new_unchecked(header.cast::<u8>().as_ptr().add(offset));
+        channel_at::<T, _>(allocation, RaceAllocator { header })
+    };
+    let race = RaceSender {
+        header,
+        channel_ptr: sender.channel_ptr,
+    };
+    // The race senders share the role of the sender, see `RaceSender::send` and `Drop`.
+    mem::forget(sender);
+    (race, receiver)
+}
+
+/// Sending end of a oneshot channel with multiple senders. Created and returned from the
+/// [`race`] function.
+///
+/// Clones share the same channel. Only the first message sent by any of them reaches the
+/// [`Receiver`].
+///
+/// The clones are counted next to the channel, in the allocation made by [`race`]. The first one
+/// to send takes over the role of the channel's [`Sender`], and the last one to be dropped does
+/// so if nobody sent anything. The other clones never touch the channel itself.
+pub struct RaceSender<T> {
+    header: NonNull<Header>,
+    channel_ptr: NonNull<Channel<T>>,
+}
+
+// SAFETY: The message is moved to the thread receiving it, and the shared state is thread safe.
+unsafe impl<T: Send> Send for RaceSender<T> {}
+
+// SAFETY: Through a shared reference the sender can only be cloned, and the clone then sends the
+// message from whichever thread it is on. So sharing a sender only requires `T: Send`.
+unsafe impl<T: Send> Sync for RaceSender<T> {}
+
+impl<T> RaceSender<T> {
+    /// Sends `message` over the channel to the corresponding [`Receiver`], if no clone of this
+    /// sender has sent anything yet.
+    ///
+    /// Returns an error holding the message if another clone already sent a message, or if the
+    /// receiver has been dropped or [closed](Receiver::close). Apart from that this behaves like
+    /// [`Sender::send`].
+    pub fn send(self, message: T) -> Result<(), SendError<T>> {
+        // SAFETY: the header is alive as long as any clone is.
+        let senders = &unsafe { self.header.as_ref() }.senders;
+        // ORDERING: winning removes us from the count like dropping does, so it releases our
+        // accesses to the header in the same way. Losing leaves that to `Drop`.
+        let won = senders
+            .fetch_update(AcqRel, Acquire, |senders| {
+                (senders & WON == 0).then(|| (senders | WON) - 1)
+            })
+            .is_ok();
+        if !won {
+            mem::drop(self);
+            return Err(SendError::unsent(message));
+        }
+
+        let sender = Sender::<T, RaceAllocator> {
+            channel_ptr: self.channel_ptr,
+            _invariant: PhantomData,
+            _alloc: PhantomData,
+        };
+        // We were removed from the count above.
+        mem::forget(self);
+        sender
+            .send(message)
+            .map_err(|error| SendError::unsent(error.into_inner()))
+    }
+}
+
+impl<T> Clone for RaceSender<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: the header is alive as long as any clone is.
+        let senders = &unsafe { self.header.as_ref() }.senders;
+        // ORDERING: a new clone only needs the allocation to stay alive, which our own count
+        // already ensures.
+        let previous = senders.fetch_add(1, Relaxed);
+        // Like `Arc`, keep the count far from overflowing into the flags. The count stays raised,
+        // so the allocation is leaked rather than freed early.
+        assert!(
+            previous & SENDERS < SENDERS / 2,
+            "too many clones of a RaceSender"
+        );
+        Self {
+            header: self.header,
+            channel_ptr: self.channel_ptr,
+        }
+    }
+}
+
+impl<T> Drop for RaceSender<T> {
+    fn drop(&mut self) {
+        // SAFETY: the header is alive as long as any clone is.
+        let senders = &unsafe { self.header.as_ref() }.senders;
+        // ORDERING: release our accesses to the header to whoever frees it, and synchronize with
+        // the accesses of everybody else in case that is us.
+        let previous = senders.fetch_sub(1, AcqRel);
+        if previous & SENDERS != 1 {
+            return;
+        }
+        if previous & WON == 0 {
+            // We are the last clone and nobody sent anything. Dropping the sender disconnects
+            // the receiver, and frees the channel if the receiver is already gone.
+            mem::drop(Sender::<T, RaceAllocator> {
+                channel_ptr: self.channel_ptr,
+                _invariant: PhantomData,
+                _alloc: PhantomData,
+            });
+        } else if previous & FREED != 0 {
+            // The winner and the receiver are done with the channel, and left releasing the
+            // allocation to us.
+            // SAFETY: nobody else accesses the allocation anymore.
+            unsafe { Header::free(self.header) };
+        }
+    }
+}
+
+impl<T> fmt::Debug for RaceSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RaceSender").finish_non_exhaustive()
+    }
+}
+
+/// The [`Allocator`] of channels created by [`race`]. Keeps the shared allocation until both the
+/// channel has been freed and the last [`RaceSender`] is gone.
+pub struct RaceAllocator {
+    header: NonNull<Header>,
+}
+
+// SAFETY: the header is thread safe.
+unsafe impl Send for RaceAllocator {}
+unsafe impl Sync for RaceAllocator {}
+
+unsafe impl Allocator for RaceAllocator {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        // SAFETY: the header is alive until the channel is freed, which is now.
+        let senders = &self.header.as_ref().senders;
+        // ORDERING: we use acquire-release ordering so whoever releases the allocation
+        // synchronizes with the accesses of everybody else.
+        if senders.fetch_or(FREED, AcqRel) & SENDERS == 0 {
+            Header::free(self.header);
+        }
+    }
+}
+
+impl fmt::Debug for RaceAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RaceAllocator").finish_non_exhaustive()
+    }
+}
+
+/// The start of a race allocation. Followed by the channel allocation.
+struct Header {
+    /// The number of `RaceSender`s alive, and the `WON` and `FREED` flags.
+    senders: AtomicU32,
+    /// The layout of the whole race allocation.
+    layout: Layout,
+}
+
+impl Header {
+    /// Drops the header and releases the race allocation.
+    ///
+    /// # Safety
+    ///
+    /// The channel must have been freed, no `RaceSender` may be alive, and `header` must not be
+    /// used after this call.
+    unsafe fn free(header: NonNull<Header>) {
+        let layout = header.as_ref().layout;
+        ptr::drop_in_place(header.as_ptr());
+        Global.deallocate(header.cast(), layout);
+    }
+}
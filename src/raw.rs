@@ -0,0 +1,282 @@
+use crate::{Channel, Receiver, Sender};
+use core::fmt;
+use core::ptr::NonNull;
+
+#[cfg(all(any(debug_assertions, feature = "checked-raw"), not(oneshot_loom)))]
+use core::sync::atomic::{AtomicU8, Ordering::*};
+#[cfg(all(any(debug_assertions, feature = "checked-raw"), oneshot_loom))]
+use loom::sync::atomic::{AtomicU8, Ordering::*};
+
+/// A [`Sender`] in raw form, from [`Sender::into_raw_sender`]. Unlike the untyped pointer from
+/// [`Sender::into_raw`], it keeps the message type, so it can't be turned back into a sender of
+/// the wrong type by mistake.
+///
+/// The handle is just a pointer to the channel. It is `Copy` so it can be stored in FFI structs,
+/// but only one copy may ever be turned back into a [`Sender`], with [`RawSender::into_sender`].
+///
+/// In debug builds, or with the `checked-raw` feature, the channel remembers its message type and
+/// which of its endpoints are in raw form. Turning a raw handle back into an endpoint then panics
+/// if the message type differs, or if that endpoint was already reconstructed, as long as the
+/// channel has not been freed yet. The check is best-effort: message types are compared by their
+/// [`type_name`](core::any::type_name), so types with the same name, like ones only differing in
+/// lifetimes or from two versions of a crate, are not told apart.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// let (sender, receiver) = oneshot::channel::<u32>();
+/// let raw = sender.into_raw_sender();
+///
+/// // Hand `raw.as_ptr()` to foreign code, and get it back later.
+/// let ptr = raw.as_ptr();
+/// let raw = unsafe { oneshot::RawSender::<u32>::from_ptr(ptr) };
+///
+/// let sender = unsafe { raw.into_sender() };
+/// sender.send(5).unwrap();
+/// assert_eq!(receiver.try_recv(), Ok(5));
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct RawSender<T> {
+    channel_ptr: NonNull<Channel<T>>,
+}
+
+/// A [`Receiver`] in raw form, from [`Receiver::into_raw_receiver`]. See [`RawSender`].
+#[repr(transparent)]
+pub struct RawReceiver<T> {
+    channel_ptr: NonNull<Channel<T>>,
+}
+
+// SAFETY: the handles are only pointers. Using them requires `unsafe`, and the endpoints they
+// turn into are only `Send` and `Sync` for `T: Send`.
+unsafe impl<T: Send> Send for RawSender<T> {}
+unsafe impl<T: Send> Sync for RawSender<T> {}
+unsafe impl<T: Send> Send for RawReceiver<T> {}
+unsafe impl<T: Send> Sync for RawReceiver<T> {}
+
+impl<T> Sender<T> {
+    /// Consumes the Sender, returning it in raw form. Like [`Sender::into_raw`], but keeps the
+    /// message type. Memory will leak if the Sender is never reconstructed.
+    pub fn into_raw_sender(self) -> RawSender<T> {
+        RawSender {
+            // SAFETY: `into_raw` returns the non-null channel pointer.
+            channel_ptr: unsafe { NonNull::new_unchecked(self.into_raw()) }.cast(),
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Consumes the Receiver, returning it in raw form. Like [`Receiver::into_raw`], but keeps
+    /// the message type. Memory will leak if the Receiver is never reconstructed.
+    pub fn into_raw_receiver(self) -> RawReceiver<T> {
+        RawReceiver {
+            // SAFETY: `into_raw` returns the non-null channel pointer.
+            channel_ptr: unsafe { NonNull::new_unchecked(self.into_raw()) }.cast(),
+        }
+    }
+}
+
+impl<T> RawSender<T> {
+    /// Recreates the [`Sender`].
+    ///
+    /// # Safety
+    ///
+    /// The Sender must not have been reconstructed before, from this handle, a copy of it, or
+    /// its pointer.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds or with the `checked-raw` feature, if the channel sends another type than
+    /// `T`, or if the Sender was already reconstructed.
+    pub unsafe fn into_sender(self) -> Sender<T> {
+        Sender::from_raw(self.as_ptr())
+    }
+
+    /// Returns the untyped pointer to the channel, the same pointer [`Sender::into_raw`] returns.
+    pub fn as_ptr(self) -> *mut () {
+        self.channel_ptr.as_ptr().cast()
+    }
+
+    /// Creates a handle from a pointer returned by [`RawSender::as_ptr`] or [`Sender::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from one of those methods, called on a `Sender<T>` with the same message
+    /// type.
+    pub unsafe fn from_ptr(ptr: *mut ()) -> Self {
+        Self {
+            channel_ptr: NonNull::new_unchecked(ptr.cast()),
+        }
+    }
+}
+
+impl<T> RawReceiver<T> {
+    /// Recreates the [`Receiver`].
+    ///
+    /// # Safety
+    ///
+    /// The Receiver must not have been reconstructed before, from this handle, a copy of it, or
+    /// its pointer.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds or with the `checked-raw` feature, if the channel sends another type than
+    /// `T`, or if the Receiver was already reconstructed.
+    pub unsafe fn into_receiver(self) -> Receiver<T> {
+        Receiver::from_raw(self.as_ptr())
+    }
+
+    /// Returns the untyped pointer to the channel, the same pointer [`Receiver::into_raw`]
+    /// returns.
+    pub fn as_ptr(self) -> *mut () {
+        self.channel_ptr.as_ptr().cast()
+    }
+
+    /// Creates a handle from a pointer returned by [`RawReceiver::as_ptr`] or
+    /// [`Receiver::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from one of those methods, called on a `Receiver<T>` with the same message
+    /// type.
+    pub unsafe fn from_ptr(ptr: *mut ()) -> Self {
+        Self {
+            channel_ptr: NonNull::new_unchecked(ptr.cast()),
+        }
+    }
+}
+
+impl<T> Clone for RawSender<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RawSender<T> {}
+
+impl<T> Clone for RawReceiver<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RawReceiver<T> {}
+
+impl<T> fmt::Debug for RawSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawSender").field(&self.channel_ptr).finish()
+    }
+}
+
+impl<T> fmt::Debug for RawReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawReceiver")
+            .field(&self.channel_ptr)
+            .finish()
+    }
+}
+
+/// The endpoint of a channel being turned into, or back from, raw form.
+#[derive(Clone, Copy)]
+pub(crate) enum End {
+    Sender,
+    Receiver,
+}
+
+/// Turned into a no-op when raw pointers are not checked.
+#[cfg(not(any(debug_assertions, feature = "checked-raw")))]
+#[inline(always)]
+pub(crate) unsafe fn leave<T>(_channel: NonNull<Channel<T>>, _end: End) {}
+
+/// Turned into a no-op when raw pointers are not checked.
+#[cfg(not(any(debug_assertions, feature = "checked-raw")))]
+#[inline(always)]
+pub(crate) unsafe fn enter<T>(_raw: *mut (), _end: End) {}
+
+/// Records that `end` of the channel is now in raw form.
+///
+/// # Safety
+///
+/// `channel` must point to a live channel.
+#[cfg(any(debug_assertions, feature = "checked-raw"))]
+pub(crate) unsafe fn leave<T>(channel: NonNull<Channel<T>>, end: End) {
+    RawCheck::of(channel.as_ptr().cast()).leave(end)
+}
+
+/// Checks that `raw` points to a channel sending `T` whose `end` is in raw form, and records that
+/// it no longer is. Panics otherwise.
+///
+/// # Safety
+///
+/// `raw` must point to a live channel, of any message type.
+#[cfg(any(debug_assertions, feature = "checked-raw"))]
+pub(crate) unsafe fn enter<T>(raw: *mut (), end: End) {
+    RawCheck::of(raw).enter::<T>(end)
+}
+
+/// Stored first in every channel when raw pointers are checked. Its position does not depend on
+/// the message type, so it can be read through a pointer to a channel of the wrong type.
+#[cfg(any(debug_assertions, feature = "checked-raw"))]
+pub(crate) struct RawCheck {
+    /// The type name of the message, to tell channels of different types apart. `TypeId` would
+    /// be exact, but requires the message type to be `'static`.
+    message_type: &'static str,
+    /// Which endpoints are in raw form.
+    raw_ends: AtomicU8,
+}
+
+#[cfg(any(debug_assertions, feature = "checked-raw"))]
+impl RawCheck {
+    pub(crate) fn new<T>() -> Self {
+        Self {
+            message_type: core::any::type_name::<T>(),
+            raw_ends: AtomicU8::new(0),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `channel` must point to a live channel, of any message type.
+    unsafe fn of<'a>(channel: *mut ()) -> &'a Self {
+        // The channel is `repr(C)` with the check as its first field.
+        &*channel.cast::<Self>()
+    }
+
+    fn leave(&self, end: End) {
+        // ORDERING: the flags are only used to detect misuse. Handing the raw pointer over to
+        // whoever reconstructs the endpoint synchronizes the rest of the channel.
+        self.raw_ends.fetch_or(Self::flag(end), Relaxed);
+    }
+
+    fn enter<T>(&self, end: End) {
+        let message_type = core::any::type_name::<T>();
+        if self.message_type != message_type {
+            panic!(
+                "oneshot {} of a channel of `{}` reconstructed from a raw pointer as a {0} of `{}`",
+                Self::name(end),
+                self.message_type,
+                message_type
+            );
+        }
+        if self.raw_ends.fetch_and(!Self::flag(end), Relaxed) & Self::flag(end) == 0 {
+            panic!(
+                "oneshot {} reconstructed from a raw pointer, but it is not in raw form. Was it \
+                 already reconstructed?",
+                Self::name(end)
+            );
+        }
+    }
+
+    fn flag(end: End) -> u8 {
+        match end {
+            End::Sender => 0b01,
+            End::Receiver => 0b10,
+        }
+    }
+
+    fn name(end: End) -> &'static str {
+        match end {
+            End::Sender => "Sender",
+            End::Receiver => "Receiver",
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Channels living in caller owned memory instead of on the heap.
+//!
+//! A [`ChannelSlot`] holds the state of a single channel. Splitting it into a [`Sender`] and
+//! [`Receiver`] borrows the slot for as long as the endpoints are alive, so they can not outlive
+//! it. This makes it possible to place the channel on the stack, for example in fork-join style
+//! code using `std::thread::scope`:
+//!
+//! ```rust
+//! # #[cfg(all(feature = "std", not(feature = "loom")))] {
+//! let mut slot = oneshot::scoped::ChannelSlot::new();
+//! let (sender, receiver) = slot.split();
+//! std::thread::scope(|scope| {
+//!     scope.spawn(move || sender.send(1234).unwrap());
+//!     assert_eq!(receiver.recv(), Ok(1234));
+//! });
+//! # }
+//! ```
+//!
+//! The endpoints behave exactly like the ones from [`channel`](crate::channel). The only
+//! difference is that the last one to be dropped leaves the memory to the slot instead of
+//! freeing it. A slot can be split again once both endpoints from the previous split are gone.
+//!
+//! A [`StaticChannel`] is the same thing, but shared instead of borrowed mutably, so it can be
+//! placed in a `static` and hand out `'static` endpoints without any allocator.
+
+use crate::{channel_at, AllocError, Allocation, Allocator};
+use core::alloc::Layout;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+
+#[cfg(not(oneshot_loom))]
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicU8, Ordering::*},
+};
+#[cfg(oneshot_loom)]
+use loom::sync::atomic::{AtomicU8, Ordering::*};
+
+/// Sending end of a channel in a [`ChannelSlot`] or [`StaticChannel`].
+pub type Sender<'a, T> = crate::Sender<T, Borrowed<'a>>;
+
+/// Receiving end of a channel in a [`ChannelSlot`] or [`StaticChannel`].
+pub type Receiver<'a, T> = crate::Receiver<T, Borrowed<'a>>;
+
+/// Error returned from sending on a closed channel in a [`ChannelSlot`] or [`StaticChannel`].
+pub type SendError<'a, T> = crate::SendError<T, Borrowed<'a>>;
+
+/// Caller owned memory for a oneshot channel sending `T`. See the [module level docs](self).
+pub struct ChannelSlot<T> {
+    // The lifetime of the allocator does not matter for the layout. `split` hands out endpoints
+    // with the lifetime of the borrow of the slot.
+    allocation: MaybeUninit<Allocation<T, Borrowed<'static>>>,
+}
+
+impl<T> ChannelSlot<T> {
+    /// Creates memory for a channel. No channel exists in it until the slot is [split].
+    ///
+    /// [split]: ChannelSlot::split
+    pub const fn new() -> Self {
+        Self {
+            allocation: MaybeUninit::uninit(),
+        }
+    }
+
+    /// Creates a new oneshot channel in this slot and returns the two endpoints, [`Sender`] and
+    /// [`Receiver`]. The slot stays borrowed until both endpoints have been dropped.
+    pub fn split(&mut self) -> (Sender<'_, T>, Receiver<'_, T>) {
+        let allocation = NonNull::from(&mut self.allocation).cast::<u8>();
+        // SAFETY: the slot fits the allocation of a channel sending `T`, whatever the lifetime of
+        // the allocator. The endpoints from any previous split are gone, since they borrowed the
+        // slot mutably, and they never access the memory after freeing it. Freeing it with the
+        // `Borrowed` allocator does nothing, leaving the memory to the slot.
+        unsafe {
+            channel_at(
+                allocation,
+                Borrowed {
+                    released: None,
+                    _slot: PhantomData,
+                },
+            )
+        }
+    }
+}
+
+impl<T> Default for ChannelSlot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for ChannelSlot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelSlot").finish_non_exhaustive()
+    }
+}
+
+/// A oneshot channel sending `T` that can be placed in a `static`. See the
+/// [module level docs](self).
+///
+/// The channel can be [split](StaticChannel::split) into its endpoints once. After both
+/// endpoints are gone the channel can be [reset](StaticChannel::reset) to be split again.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// use oneshot::scoped::StaticChannel;
+///
+/// static HANDSHAKE: StaticChannel<u32> = StaticChannel::new();
+///
+/// let (sender, receiver) = HANDSHAKE.split().unwrap();
+/// assert!(HANDSHAKE.split().is_none());
+///
+/// sender.send(1).unwrap();
+/// assert_eq!(receiver.try_recv(), Ok(1));
+/// drop(receiver);
+///
+/// assert!(HANDSHAKE.reset());
+/// assert!(HANDSHAKE.split().is_some());
+/// # }
+/// ```
+#[cfg(not(oneshot_loom))]
+pub struct StaticChannel<T> {
+    allocation: UnsafeCell<MaybeUninit<Allocation<T, Borrowed<'static>>>>,
+    state: AtomicU8,
+}
+
+/// The channel can be split.
+#[cfg(not(oneshot_loom))]
+const AVAILABLE: u8 = 0;
+/// The channel has been split and at least one of the endpoints is still alive.
+#[cfg(not(oneshot_loom))]
+const IN_USE: u8 = 1;
+/// Both endpoints from the last split are gone, but the channel has not been reset yet.
+const RELEASED: u8 = 2;
+
+// SAFETY: The channel memory is only accessed through the endpoints, which `split` hands out only
+// once until they are gone.
+#[cfg(not(oneshot_loom))]
+unsafe impl<T: Send> Sync for StaticChannel<T> {}
+
+#[cfg(not(oneshot_loom))]
+impl<T> StaticChannel<T> {
+    /// Creates a channel that can be split into its endpoints.
+    pub const fn new() -> Self {
+        Self {
+            allocation: UnsafeCell::new(MaybeUninit::uninit()),
+            state: AtomicU8::new(AVAILABLE),
+        }
+    }
+
+    /// Returns the two endpoints, [`Sender`] and [`Receiver`], of a new oneshot channel. Returns
+    /// `None` if the channel has already been split and not [reset](StaticChannel::reset) since.
+    pub fn split(&self) -> Option<(Sender<'_, T>, Receiver<'_, T>)> {
+        // ORDERING: we use acquire ordering to synchronize with the accesses of the endpoints of
+        // the previous split, which `reset` synchronized with.
+        if self
+            .state
+            .compare_exchange(AVAILABLE, IN_USE, Acquire, Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+
+        let allocation = NonNull::new(self.allocation.get()).unwrap().cast::<u8>();
+        // SAFETY: the memory fits the allocation of a channel sending `T`, whatever the lifetime
+        // of the allocator. We just switched from AVAILABLE to IN_USE, so no other endpoints use
+        // the memory. Freeing it with the `Borrowed` allocator only marks the channel released.
+        Some(unsafe {
+            channel_at(
+                allocation,
+                Borrowed {
+                    released: Some(&self.state),
+                    _slot: PhantomData,
+                },
+            )
+        })
+    }
+
+    /// Makes the channel available to be [split](StaticChannel::split) again. Returns true if
+    /// the channel is now available, and false if endpoints from the last split are still alive.
+    pub fn reset(&self) -> bool {
+        // ORDERING: we use acquire ordering to synchronize with the release of the channel by
+        // the last endpoint.
+        match self
+            .state
+            .compare_exchange(RELEASED, AVAILABLE, AcqRel, Relaxed)
+        {
+            Ok(_) | Err(AVAILABLE) => true,
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(not(oneshot_loom))]
+impl<T> Default for StaticChannel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(oneshot_loom))]
+impl<T> fmt::Debug for StaticChannel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticChannel").finish_non_exhaustive()
+    }
+}
+
+/// The [`Allocator`] of channels in a [`ChannelSlot`] or [`StaticChannel`]. The memory is owned
+/// by the slot, so this never allocates nor frees anything.
+#[derive(Debug)]
+pub struct Borrowed<'a> {
+    /// Set to RELEASED when the channel is freed. Used by `StaticChannel`.
+    released: Option<&'a AtomicU8>,
+    _slot: PhantomData<&'a mut ()>,
+}
+
+unsafe impl Allocator for Borrowed<'_> {
+    #[inline]
+    fn allocate(&self, _layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        Err(AllocError)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        if let Some(state) = self.released {
+            // ORDERING: we use release ordering so `StaticChannel::reset` can synchronize with
+            // all accesses to the channel.
+            state.store(RELEASED, Release);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{states::*, thread, Allocator, Receiver, ReceiverWaker};
+use core::fmt;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+#[cfg(not(oneshot_loom))]
+use core::sync::atomic::Ordering::*;
+#[cfg(oneshot_loom)]
+use loom::sync::atomic::Ordering::*;
+
+/// Blocks the current thread until any of several receivers, of any message types, is ready.
+///
+/// Add the receivers with [`Select::recv`], then wait with [`Select::wait`] or its siblings.
+/// These return the index of a receiver that is ready, meaning that its message has arrived or
+/// that it is disconnected. Receive the message with [`Receiver::try_recv`] on that receiver.
+/// The other receivers are left untouched, and can be waited for again.
+///
+/// While waiting, the thread is registered with every receiver, the same way a blocking receive
+/// registers it. So a receiver that was polled as a future before forgets that task.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// let (sender_a, receiver_a) = oneshot::channel::<u32>();
+/// let (_sender_b, receiver_b) = oneshot::channel::<String>();
+///
+/// std::thread::spawn(move || sender_a.send(5));
+///
+/// let mut select = oneshot::Select::new();
+/// let a = select.recv(&receiver_a);
+/// let b = select.recv(&receiver_b);
+/// match select.wait() {
+///     i if i == a => assert_eq!(receiver_a.try_recv(), Ok(5)),
+///     i if i == b => unreachable!("the sender of b is still alive"),
+///     _ => unreachable!(),
+/// }
+/// assert!(receiver_b.try_recv().is_err());
+/// # }
+/// ```
+pub struct Select<'a> {
+    receivers: Vec<&'a (dyn Selectable + 'a)>,
+}
+
+impl<'a> Select<'a> {
+    /// Creates a new `Select` without any receivers.
+    pub const fn new() -> Self {
+        Self {
+            receivers: Vec::new(),
+        }
+    }
+
+    /// Adds `receiver` to the receivers to wait for, and returns its index. Indices are given
+    /// out in the order the receivers are added, starting at zero.
+    pub fn recv<T, A: Allocator>(&mut self, receiver: &'a Receiver<T, A>) -> usize {
+        self.receivers.push(receiver);
+        self.receivers.len() - 1
+    }
+
+    /// Blocks the current thread until any of the receivers is ready, and returns its index. If
+    /// more than one receiver is ready, the one added first is returned.
+    ///
+    /// Blocks forever if no receivers were added.
+    pub fn wait(&self) -> usize {
+        self.wait_until(None).unwrap()
+    }
+
+    /// Like [`Select::wait`], but will not block longer than `timeout`. Returns `None` if no
+    /// receiver was ready before the timeout was reached.
+    ///
+    /// If the supplied `timeout` is so large that Rust's `Instant` type can't represent this point
+    /// in the future this falls back to an indefinitely blocking wait.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<usize> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.wait_deadline(deadline),
+            None => Some(self.wait()),
+        }
+    }
+
+    /// Like [`Select::wait`], but will not block longer than until `deadline`. Returns `None` if
+    /// no receiver was ready before the deadline was reached.
+    pub fn wait_deadline(&self, deadline: Instant) -> Option<usize> {
+        self.wait_until(Some(deadline))
+    }
+
+    fn wait_until(&self, deadline: Option<Instant>) -> Option<usize> {
+        loop {
+            // Register our thread with every receiver, unless one is already ready. The first one
+            // to change the state of its channel wakes us up.
+            let registered = self
+                .receivers
+                .iter()
+                .position(|receiver| !receiver.register())
+                .unwrap_or(self.receivers.len());
+
+            let mut timed_out = false;
+            if registered == self.receivers.len() {
+                match deadline {
+                    None => thread::park(),
+                    Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                        Some(timeout) => thread::park_timeout(timeout),
+                        None => timed_out = true,
+                    },
+                }
+            }
+
+            // Take our thread back out of every channel, so all receivers are left as they were.
+            for receiver in &self.receivers[..registered] {
+                receiver.unregister();
+            }
+            if let Some(index) = self
+                .receivers
+                .iter()
+                .position(|receiver| receiver.is_ready())
+            {
+                return Some(index);
+            }
+            if timed_out {
+                return None;
+            }
+        }
+    }
+}
+
+impl Default for Select<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Select<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select")
+            .field("receivers", &self.receivers.len())
+            .finish()
+    }
+}
+
+/// A receiver of any message type, as seen by [`Select`].
+trait Selectable {
+    /// Registers the current thread to be woken up when the channel changes state. Returns false,
+    /// without registering, if the receiver is already ready.
+    fn register(&self) -> bool;
+
+    /// Undoes a successful [`Selectable::register`].
+    fn unregister(&self);
+
+    /// Returns true if a receive would not find the channel empty.
+    fn is_ready(&self) -> bool;
+}
+
+impl<T, A: Allocator> Selectable for Receiver<T, A> {
+    fn register(&self) -> bool {
+        // SAFETY: the receiver is alive, so the channel is valid.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        // ORDERING: we do not access the message, so no synchronization is needed. A later
+        // receive synchronizes with the write of the message.
+        match channel.state.load(Relaxed) {
+            EMPTY | WRITING => (),
+            // The receiver was `Future::poll`ed prior to this call. Take back the task waker so
+            // we can store our thread waker instead.
+            // SAFETY: we are the receiver.
+            #[cfg(feature = "async")]
+            RECEIVING | UNPARKING | WRITING_RECEIVING => {
+                unsafe { channel.reclaim_waker() };
+                return self.register();
+            }
+            _ => return false,
+        }
+
+        // Let a sender waiting in `Sender::wait_for_demand` know that we are waiting.
+        // SAFETY: we are the receiver.
+        unsafe { channel.notify_demand() };
+
+        // SAFETY: we are the receiver, the channel is EMPTY or WRITING and so holds no waker.
+        unsafe { channel.register_waker(ReceiverWaker::current_thread()) }
+    }
+
+    fn unregister(&self) {
+        // SAFETY: the receiver is alive, so the channel is valid. We are the receiver.
+        unsafe { self.channel_ptr.as_ref().reclaim_waker() }
+    }
+
+    fn is_ready(&self) -> bool {
+        // SAFETY: the receiver is alive, so the channel is valid.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        // ORDERING: see `register`.
+        !matches!(
+            channel.state.load(Relaxed),
+            EMPTY | WRITING | RECEIVING | UNPARKING | WRITING_RECEIVING
+        )
+    }
+}
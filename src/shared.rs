@@ -0,0 +1,311 @@
+use crate::{Allocator, Global, Receiver, RecvError, RecvTimeoutError, TryRecvError};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{self, Poll, Waker};
+use std::sync::Arc;
+use std::task::Wake;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+#[cfg(oneshot_loom)]
+use loom::sync::{Mutex, MutexGuard};
+#[cfg(not(oneshot_loom))]
+use std::sync::{Mutex, MutexGuard};
+
+/// A receiver that can be cloned, so any number of threads and tasks can wait for the same
+/// message. Created with [`Receiver::shared`].
+///
+/// Every clone receives the message, behind an [`Arc`], once the [`Sender`](crate::Sender) has
+/// sent it. All clones waiting for the message are woken up at once by the send.
+///
+/// ```rust
+/// # #[cfg(not(feature = "loom"))] {
+/// let (sender, receiver) = oneshot::channel();
+/// let receiver = receiver.shared();
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|_| {
+///         let receiver = receiver.clone();
+///         std::thread::spawn(move || *receiver.recv().unwrap())
+///     })
+///     .collect();
+///
+/// sender.send(7).unwrap();
+/// for thread in threads {
+///     assert_eq!(thread.join().unwrap(), 7);
+/// }
+/// assert_eq!(receiver.get(), Some(&7));
+/// # }
+/// ```
+pub struct SharedReceiver<T, A: Allocator = Global> {
+    shared: Arc<Shared<T, A>>,
+    /// The key of the waker of this clone in the waiter list, once it has been polled.
+    key: Option<usize>,
+}
+
+struct Shared<T, A: Allocator> {
+    state: Mutex<State<T, A>>,
+    waiters: Arc<Waiters>,
+}
+
+enum State<T, A: Allocator> {
+    /// Nobody has received the message yet.
+    Waiting(Receiver<T, A>),
+    /// The message has been received. Never changes again.
+    Received(Arc<T>),
+    /// The sender was dropped without sending anything.
+    Disconnected,
+}
+
+impl<T, A: Allocator> SharedReceiver<T, A> {
+    pub(crate) fn new(receiver: Receiver<T, A>) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::Waiting(receiver)),
+                waiters: Arc::new(Waiters::new()),
+            }),
+            key: None,
+        }
+    }
+
+    /// Returns the message if it is already in the channel or has been received by any clone,
+    /// without waiting. See [`Receiver::try_recv`].
+    pub fn try_recv(&self) -> Result<Arc<T>, TryRecvError> {
+        let mut state = self.shared.lock();
+        let result = match &mut *state {
+            State::Waiting(receiver) => match receiver.try_recv() {
+                Ok(message) => Ok(message),
+                Err(TryRecvError::Empty) => return Err(TryRecvError::Empty),
+                Err(TryRecvError::Disconnected) => Err(RecvError),
+            },
+            State::Received(message) => return Ok(message.clone()),
+            State::Disconnected => return Err(TryRecvError::Disconnected),
+        };
+        let result = Shared::complete(&mut state, result);
+        drop(state);
+        self.shared.waiters.wake_all();
+        result.map_err(|RecvError| TryRecvError::Disconnected)
+    }
+
+    /// Blocks the current thread until the message is sent, and returns it. Returns an error if
+    /// the sender was dropped without sending anything. See [`Receiver::recv`].
+    pub fn recv(&self) -> Result<Arc<T>, RecvError> {
+        self.wait(None).map_err(|_| RecvError)
+    }
+
+    /// Like [`SharedReceiver::recv`], but returns a clone of the message.
+    pub fn recv_cloned(&self) -> Result<T, RecvError>
+    where
+        T: Clone,
+    {
+        self.recv().map(|message| T::clone(&message))
+    }
+
+    /// Like [`SharedReceiver::recv`], but will not block longer than `timeout`. See
+    /// [`Receiver::recv_timeout`].
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Arc<T>, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(deadline),
+            None => self.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        }
+    }
+
+    /// Like [`SharedReceiver::recv`], but will not block longer than until `deadline`. See
+    /// [`Receiver::recv_deadline`].
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<Arc<T>, RecvTimeoutError> {
+        self.wait(Some(deadline))
+    }
+
+    /// Returns a reference to the message if it has been received by any clone. Does not check
+    /// the channel itself, use [`SharedReceiver::try_recv`] for that.
+    pub fn get(&self) -> Option<&T> {
+        match &*self.shared.lock() {
+            // SAFETY: a received message is never replaced nor dropped while the shared state is
+            // alive, and `self` keeps the shared state alive.
+            State::Received(message) => Some(unsafe { &*Arc::as_ptr(message) }),
+            _ => None,
+        }
+    }
+
+    /// Blocks the current thread until the message arrives or `deadline` is reached.
+    fn wait(&self, deadline: Option<Instant>) -> Result<Arc<T>, RecvTimeoutError> {
+        let waker = Waker::from(Arc::new(ThreadWaker(crate::thread::current())));
+        let mut key = None;
+        let result = loop {
+            if let Poll::Ready(result) = self.shared.poll(&mut key, &waker) {
+                break result.map_err(|RecvError| RecvTimeoutError::Disconnected);
+            }
+            match deadline {
+                None => crate::thread::park(),
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) => crate::thread::park_timeout(timeout),
+                    None => break Err(RecvTimeoutError::Timeout),
+                },
+            }
+        };
+        self.shared.waiters.remove(key);
+        result
+    }
+}
+
+impl<T, A: Allocator> Shared<T, A> {
+    fn lock(&self) -> MutexGuard<'_, State<T, A>> {
+        // A panic while holding the lock can not leave the state inconsistent.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Checks for the message, registering `waker` under `key` to be woken up by the send if it
+    /// is not there yet.
+    fn poll(&self, key: &mut Option<usize>, waker: &Waker) -> Poll<Result<Arc<T>, RecvError>> {
+        let mut state = self.lock();
+        let receiver = match &mut *state {
+            State::Waiting(receiver) => receiver,
+            State::Received(message) => return Poll::Ready(Ok(message.clone())),
+            State::Disconnected => return Poll::Ready(Err(RecvError)),
+        };
+
+        // Register before polling the receiver, so a send right after the poll wakes us up.
+        self.waiters.insert(key, waker);
+
+        // The receiver only holds a single waker. Let it wake up all the waiters.
+        let wake_all = Waker::from(self.waiters.clone());
+        let mut cx = task::Context::from_waker(&wake_all);
+        let result = match Pin::new(receiver).poll(&mut cx) {
+            Poll::Ready(result) => Self::complete(&mut state, result),
+            Poll::Pending => return Poll::Pending,
+        };
+        drop(state);
+        self.waiters.wake_all();
+        Poll::Ready(result)
+    }
+
+    /// Stores the result of the receive for all clones. The caller must wake up the other
+    /// waiters after releasing the lock.
+    fn complete(
+        state: &mut State<T, A>,
+        result: Result<T, RecvError>,
+    ) -> Result<Arc<T>, RecvError> {
+        let result = result.map(Arc::new);
+        *state = match &result {
+            Ok(message) => State::Received(message.clone()),
+            Err(RecvError) => State::Disconnected,
+        };
+        result
+    }
+}
+
+impl<T, A: Allocator> Clone for SharedReceiver<T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            key: None,
+        }
+    }
+}
+
+impl<T, A: Allocator> Drop for SharedReceiver<T, A> {
+    fn drop(&mut self) {
+        self.shared.waiters.remove(self.key);
+    }
+}
+
+impl<T, A: Allocator> Future for SharedReceiver<T, A> {
+    type Output = Result<Arc<T>, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        this.shared.poll(&mut this.key, cx.waker())
+    }
+}
+
+impl<T, A: Allocator> fmt::Debug for SharedReceiver<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedReceiver").finish_non_exhaustive()
+    }
+}
+
+/// The wakers of all clones waiting for the message. Used as the waker of the receiver, waking
+/// everyone up when the message is sent.
+struct Waiters {
+    wakers: Mutex<WakerList>,
+}
+
+struct WakerList {
+    /// Each waker with its key. Few clones wait at the same time, so a list is fast enough.
+    wakers: Vec<(usize, Waker)>,
+    next_key: usize,
+}
+
+impl Waiters {
+    fn new() -> Self {
+        Self {
+            wakers: Mutex::new(WakerList {
+                wakers: Vec::new(),
+                next_key: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WakerList> {
+        self.wakers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Stores `waker` under `key`, picking a new key if there is none yet.
+    fn insert(&self, key: &mut Option<usize>, waker: &Waker) {
+        let mut list = self.lock();
+        let key = match *key {
+            Some(key) => key,
+            None => {
+                let new_key = list.next_key;
+                list.next_key += 1;
+                *key = Some(new_key);
+                new_key
+            }
+        };
+        match list.wakers.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, stored)) => {
+                if !stored.will_wake(waker) {
+                    *stored = waker.clone();
+                }
+            }
+            None => list.wakers.push((key, waker.clone())),
+        }
+    }
+
+    fn remove(&self, key: Option<usize>) {
+        if let Some(key) = key {
+            self.lock().wakers.retain(|(k, _)| *k != key);
+        }
+    }
+
+    fn wake_all(&self) {
+        let wakers: Vec<Waker> = self.lock().wakers.iter().map(|(_, w)| w.clone()).collect();
+        // Wake outside the lock, in case a waker polls right away.
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Wake for Waiters {
+    fn wake(self: Arc<Self>) {
+        self.wake_all()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_all()
+    }
+}
+
+/// Wakes up a thread blocked in a receive method of [`SharedReceiver`] or
+/// [`ReceiverGroup`](crate::ReceiverGroup).
+pub(crate) struct ThreadWaker(pub(crate) crate::thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark()
+    }
+}
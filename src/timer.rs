@@ -0,0 +1,329 @@
+//! Async receive with a timeout, on any async runtime.
+//!
+//! [`Receiver::timeout_with`](crate::Receiver::timeout_with) and
+//! [`Receiver::deadline_with`](crate::Receiver::deadline_with) return a [`Timeout`] future,
+//! racing the message against a sleep from a [`Timer`]. Implement [`Timer`] for the timer of
+//! your runtime, or use one of the built in timers behind cargo features:
+//!
+//! * `timer-thread`: `ThreadTimer`, sleeping with a background thread. Works with any runtime,
+//!   and is used by `Receiver::timeout` and `Receiver::deadline`.
+//! * `tokio`: `TokioTimer`, sleeping with the tokio timer.
+//! * `async-std`: `AsyncStdTimer`, sleeping with the async-std timer.
+//!
+//! When the timeout is reached, the [`Receiver`] stays usable, just like after
+//! `Receiver::recv_timeout`. A message sent later can still be received.
+
+use crate::{Allocator, Receiver, RecvError, RecvTimeoutError, TryRecvError};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{self, Poll};
+use core::time::Duration;
+
+/// Creates futures completing at a given time. Used by [`Timeout`] to stop waiting for the
+/// message.
+pub trait Timer {
+    /// A point in time on the clock of this timer.
+    type Instant;
+
+    /// The future returned from [`Timer::sleep`] and [`Timer::sleep_until`].
+    type Sleep: Future<Output = ()>;
+
+    /// Returns a future completing after `duration` has passed.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+
+    /// Returns a future completing at `deadline`.
+    fn sleep_until(&self, deadline: Self::Instant) -> Self::Sleep;
+}
+
+impl<Tm: Timer + ?Sized> Timer for &Tm {
+    type Instant = Tm::Instant;
+    type Sleep = Tm::Sleep;
+
+    #[inline]
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        (**self).sleep(duration)
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: Self::Instant) -> Self::Sleep {
+        (**self).sleep_until(deadline)
+    }
+}
+
+/// Future returned from [`Receiver::timeout_with`](crate::Receiver::timeout_with) and
+/// [`Receiver::deadline_with`](crate::Receiver::deadline_with). Completes with the message, or
+/// with [`RecvTimeoutError::Timeout`] once the sleep `S` completes.
+#[derive(Debug)]
+pub struct Timeout<'a, T, A: Allocator, S> {
+    receiver: &'a mut Receiver<T, A>,
+    sleep: S,
+}
+
+impl<'a, T, A: Allocator, S> Timeout<'a, T, A, S> {
+    pub(crate) fn new(receiver: &'a mut Receiver<T, A>, sleep: S) -> Self {
+        Self { receiver, sleep }
+    }
+}
+
+impl<T, A: Allocator, S: Future<Output = ()>> Future for Timeout<'_, T, A, S> {
+    type Output = Result<T, RecvTimeoutError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `sleep` is structurally pinned. It is never moved out of the pinned future, and
+        // we have no Drop implementation that could move it. The receiver is not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let sleep = unsafe { Pin::new_unchecked(&mut this.sleep) };
+
+        if let Poll::Ready(result) = Pin::new(&mut *this.receiver).poll(cx) {
+            return Poll::Ready(result.map_err(|RecvError| RecvTimeoutError::Disconnected));
+        }
+        match sleep.poll(cx) {
+            Poll::Ready(()) => {
+                // Take our task waker back out of the channel, so the receiver is left as if it
+                // had never been polled. The message might have arrived in the meantime.
+                // SAFETY: we hold the receiver.
+                unsafe { this.receiver.channel_ptr.as_ref().reclaim_waker() };
+                let result = match this.receiver.try_recv() {
+                    Ok(message) => Ok(message),
+                    Err(TryRecvError::Empty) => Err(RecvTimeoutError::Timeout),
+                    Err(TryRecvError::Disconnected) => Err(RecvTimeoutError::Disconnected),
+                };
+                #[cfg(feature = "tracing")]
+                if result.is_err() {
+                    this.receiver.trace().received(&result);
+                }
+                Poll::Ready(result)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "timer-thread")]
+pub use thread_timer::{ThreadSleep, ThreadTimer};
+
+#[cfg(feature = "timer-thread")]
+mod thread_timer {
+    use super::Timer;
+    use alloc::collections::BTreeMap;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::ptr;
+    use core::sync::atomic::{AtomicPtr, Ordering::*};
+    use core::task::{self, Poll, Waker};
+    use core::time::Duration;
+    use std::sync::{Condvar, Mutex, MutexGuard};
+    use std::time::Instant;
+    use std::vec::Vec;
+
+    /// A [`Timer`] working with any async runtime. Sleeps are completed by a single background
+    /// thread, started the first time a sleep has to wait.
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct ThreadTimer;
+
+    impl Timer for ThreadTimer {
+        type Instant = Instant;
+        type Sleep = ThreadSleep;
+
+        fn sleep(&self, duration: Duration) -> ThreadSleep {
+            match Instant::now().checked_add(duration) {
+                Some(deadline) => self.sleep_until(deadline),
+                // Too far into the future to represent. Never complete.
+                None => ThreadSleep {
+                    deadline: None,
+                    id: None,
+                },
+            }
+        }
+
+        fn sleep_until(&self, deadline: Instant) -> ThreadSleep {
+            ThreadSleep {
+                deadline: Some(deadline),
+                id: None,
+            }
+        }
+    }
+
+    /// The sleep future of [`ThreadTimer`].
+    #[derive(Debug)]
+    pub struct ThreadSleep {
+        /// `None` if the sleep never completes.
+        deadline: Option<Instant>,
+        /// The key of our waker in the timer thread, once registered.
+        id: Option<u64>,
+    }
+
+    impl Future for ThreadSleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<()> {
+            let deadline = match self.deadline {
+                Some(deadline) => deadline,
+                None => return Poll::Pending,
+            };
+            if Instant::now() >= deadline {
+                return Poll::Ready(());
+            }
+
+            let shared = Shared::get();
+            let mut sleepers = shared.lock();
+            let id = match self.id {
+                Some(id) => id,
+                None => {
+                    let id = sleepers.next_id;
+                    sleepers.next_id += 1;
+                    self.id = Some(id);
+                    id
+                }
+            };
+            let is_next = sleepers
+                .wakers
+                .keys()
+                .next()
+                .map_or(true, |&(next, _)| deadline < next);
+            sleepers.wakers.insert((deadline, id), cx.waker().clone());
+            drop(sleepers);
+            if is_next {
+                shared.condvar.notify_one();
+            }
+            Poll::Pending
+        }
+    }
+
+    impl Drop for ThreadSleep {
+        fn drop(&mut self) {
+            if let (Some(deadline), Some(id)) = (self.deadline, self.id) {
+                Shared::get().lock().wakers.remove(&(deadline, id));
+            }
+        }
+    }
+
+    /// The state shared between all sleeps and the timer thread.
+    struct Shared {
+        sleepers: Mutex<Sleepers>,
+        condvar: Condvar,
+    }
+
+    struct Sleepers {
+        /// The wakers of all pending sleeps, ordered by deadline.
+        wakers: BTreeMap<(Instant, u64), Waker>,
+        next_id: u64,
+    }
+
+    static SHARED: AtomicPtr<Shared> = AtomicPtr::new(ptr::null_mut());
+
+    impl Shared {
+        /// Returns the shared state, starting the timer thread if this is the first call.
+        fn get() -> &'static Shared {
+            // ORDERING: synchronize with the initialization of the state.
+            let shared = SHARED.load(Acquire);
+            if !shared.is_null() {
+                // SAFETY: once set, the pointer is never changed nor freed.
+                return unsafe { &*shared };
+            }
+
+            let new = alloc::boxed::Box::into_raw(alloc::boxed::Box::new(Shared {
+                sleepers: Mutex::new(Sleepers {
+                    wakers: BTreeMap::new(),
+                    next_id: 0,
+                }),
+                condvar: Condvar::new(),
+            }));
+            // ORDERING: release our initialization of the state, or acquire the one of the
+            // thread that got here first.
+            match SHARED.compare_exchange(ptr::null_mut(), new, AcqRel, Acquire) {
+                Ok(_) => {
+                    // SAFETY: we just stored the pointer, and it is never freed.
+                    let shared = unsafe { &*new };
+                    std::thread::Builder::new()
+                        .name("oneshot-timer".into())
+                        .spawn(move || shared.run())
+                        .expect("failed to spawn the oneshot timer thread");
+                    shared
+                }
+                Err(existing) => {
+                    // SAFETY: nobody else saw our state.
+                    drop(unsafe { alloc::boxed::Box::from_raw(new) });
+                    // SAFETY: once set, the pointer is never changed nor freed.
+                    unsafe { &*existing }
+                }
+            }
+        }
+
+        fn lock(&self) -> MutexGuard<'_, Sleepers> {
+            // A panic while holding the lock can not leave the sleepers inconsistent.
+            self.sleepers.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        /// The timer thread. Wakes up sleeps as their deadlines pass.
+        fn run(&self) {
+            let mut expired = Vec::new();
+            loop {
+                let mut sleepers = self.lock();
+                let now = Instant::now();
+                while let Some(&key) = sleepers.wakers.keys().next() {
+                    if key.0 > now {
+                        break;
+                    }
+                    expired.extend(sleepers.wakers.remove(&key));
+                }
+                if !expired.is_empty() {
+                    // Wake outside the lock, in case a waker polls the sleep right away.
+                    drop(sleepers);
+                    expired.drain(..).for_each(Waker::wake);
+                    continue;
+                }
+                match sleepers.wakers.keys().next() {
+                    Some(&(next, _)) => {
+                        let timeout = next.saturating_duration_since(now);
+                        drop(self.condvar.wait_timeout(sleepers, timeout));
+                    }
+                    None => drop(self.condvar.wait(sleepers)),
+                }
+            }
+        }
+    }
+}
+
+/// A [`Timer`] using the tokio timer. Must be used from within a tokio runtime with the time
+/// driver enabled.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl Timer for TokioTimer {
+    type Instant = std::time::Instant;
+    type Sleep = tokio::time::Sleep;
+
+    #[inline]
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: std::time::Instant) -> Self::Sleep {
+        tokio::time::sleep_until(deadline.into())
+    }
+}
+
+/// A [`Timer`] using the async-std timer.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AsyncStdTimer;
+
+#[cfg(feature = "async-std")]
+impl Timer for AsyncStdTimer {
+    type Instant = std::time::Instant;
+    type Sleep = Pin<alloc::boxed::Box<dyn Future<Output = ()> + Send>>;
+
+    #[inline]
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        alloc::boxed::Box::pin(async_std::task::sleep(duration))
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: std::time::Instant) -> Self::Sleep {
+        self.sleep(deadline.saturating_duration_since(std::time::Instant::now()))
+    }
+}
@@ -0,0 +1,42 @@
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use tracing::Span;
+
+/// The source of channel ids. Not modelled by loom, it only hands out unique numbers.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// What the `tracing` feature stores in each channel. Every event about the channel carries its
+/// id, and is a child of the span that was current when the channel was created, if any.
+#[derive(Clone)]
+pub(crate) struct ChannelTrace {
+    pub id: usize,
+    pub span: Span,
+}
+
+impl ChannelTrace {
+    pub fn new() -> Self {
+        let trace = Self {
+            // ORDERING: the ids only need to be unique.
+            id: NEXT_ID.fetch_add(1, Relaxed),
+            span: Span::current(),
+        };
+        channel_event!(trace, TRACE, "channel created");
+        trace
+    }
+
+    /// Like `new`, for a channel no receiver ever sees. Records no event.
+    pub fn untraced() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Relaxed),
+            span: Span::none(),
+        }
+    }
+
+    /// Records the outcome of a receive that returned.
+    pub fn received<T, E: fmt::Debug>(&self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => channel_event!(self, TRACE, "message received"),
+            Err(error) => channel_event!(self, DEBUG, ?error, "receive failed"),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::mem;
+use core::ptr::NonNull;
+use oneshot::{AllocError, Allocator, Global};
+
+mod helpers;
+use helpers::{maybe_loom_model, DropCounter};
+
+/// Allocates from the global allocator, but keeps track of what is currently allocated.
+#[derive(Default)]
+struct CountingAlloc {
+    allocations: Cell<usize>,
+    deallocations: Cell<usize>,
+}
+
+unsafe impl Allocator for CountingAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        self.allocations.set(self.allocations.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.deallocations.set(self.deallocations.get() + 1);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+impl CountingAlloc {
+    fn assert_counts(&self, allocations: usize, deallocations: usize) {
+        assert_eq!(self.allocations.get(), allocations);
+        assert_eq!(self.deallocations.get(), deallocations);
+    }
+}
+
+#[test]
+fn send_and_try_recv_frees_with_allocator() {
+    maybe_loom_model(|| {
+        let alloc = CountingAlloc::default();
+        let (sender, receiver) = oneshot::channel_in::<u128, _>(&alloc);
+        alloc.assert_counts(1, 0);
+
+        assert!(sender.send(19).is_ok());
+        alloc.assert_counts(1, 0);
+        assert_eq!(receiver.try_recv(), Ok(19));
+        alloc.assert_counts(1, 0);
+
+        mem::drop(receiver);
+        alloc.assert_counts(1, 1);
+    })
+}
+
+#[test]
+fn drop_endpoints_frees_with_allocator() {
+    maybe_loom_model(|| {
+        let alloc = CountingAlloc::default();
+
+        let (sender, receiver) = oneshot::channel_in::<u128, _>(&alloc);
+        mem::drop(sender);
+        alloc.assert_counts(1, 0);
+        mem::drop(receiver);
+        alloc.assert_counts(1, 1);
+
+        let (sender, receiver) = oneshot::channel_in::<u128, _>(&alloc);
+        mem::drop(receiver);
+        alloc.assert_counts(2, 1);
+        mem::drop(sender);
+        alloc.assert_counts(2, 2);
+    })
+}
+
+#[test]
+fn send_error_frees_with_allocator() {
+    maybe_loom_model(|| {
+        let alloc = CountingAlloc::default();
+        let (message, counter) = DropCounter::new(());
+
+        let (sender, receiver) = oneshot::channel_in(&alloc);
+        mem::drop(receiver);
+        let send_error = sender.send(message).unwrap_err();
+        alloc.assert_counts(1, 0);
+
+        mem::drop(send_error);
+        assert_eq!(counter.count(), 1);
+        alloc.assert_counts(1, 1);
+    })
+}
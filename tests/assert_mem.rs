@@ -1,4 +1,4 @@
-use oneshot::{Receiver, Sender};
+use oneshot::{RawReceiver, RawSender, Receiver, Sender};
 use std::mem;
 
 /// Just sanity check that both channel endpoints stay the size of a single pointer.
@@ -17,6 +17,10 @@ fn channel_endpoints_single_pointer() {
 
     assert_eq!(mem::size_of::<Option<Sender<[u8; 1024]>>>(), PTR_SIZE);
     assert_eq!(mem::size_of::<Option<Receiver<[u8; 1024]>>>(), PTR_SIZE);
+
+    assert_eq!(mem::size_of::<RawSender<[u8; 1024]>>(), PTR_SIZE);
+    assert_eq!(mem::size_of::<RawReceiver<[u8; 1024]>>(), PTR_SIZE);
+    assert_eq!(mem::size_of::<Option<RawSender<[u8; 1024]>>>(), PTR_SIZE);
 }
 
 /// Check that the `SendError` stays small. Useful to automatically detect if it is refactored
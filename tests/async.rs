@@ -126,3 +126,118 @@ async fn poll_receiver_then_drop_it() {
     // Make sure the receiver has been dropped by the runtime.
     assert!(sender.send(()).is_err());
 }
+
+#[cfg(feature = "std")]
+#[tokio::test(flavor = "multi_thread")]
+async fn poll_receiver_in_select_then_recv_blocking() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    tokio::select! {
+        _ = &mut receiver => panic!("Nothing has been sent"),
+        _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+    }
+    let t = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        sender.send(5).unwrap();
+    });
+    let message = tokio::task::spawn_blocking(move || receiver.recv())
+        .await
+        .unwrap();
+    assert_eq!(message, Ok(5));
+    t.await.unwrap();
+}
+
+#[tokio::test]
+async fn state_after_polling_receiver() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    tokio::select! {
+        _ = &mut receiver => panic!("Nothing has been sent"),
+        _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+    }
+    assert_eq!(sender.state(), oneshot::ChannelState::ReceiverWaitingAsync);
+    assert_eq!(
+        receiver.state(),
+        oneshot::ChannelState::ReceiverWaitingAsync
+    );
+    assert!(sender.send(5).is_ok());
+    assert_eq!(receiver.state(), oneshot::ChannelState::Message);
+    assert_eq!(receiver.await, Ok(5));
+}
+
+#[cfg(feature = "std")]
+#[tokio::test(flavor = "multi_thread")]
+async fn state_after_polling_then_recv_blocking() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    tokio::select! {
+        _ = &mut receiver => panic!("Nothing has been sent"),
+        _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+    }
+    let t = tokio::task::spawn_blocking(move || receiver.recv());
+    while sender.state() != oneshot::ChannelState::ReceiverWaitingSync {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    assert!(sender.send(5).is_ok());
+    assert_eq!(t.await.unwrap(), Ok(5));
+}
+
+#[tokio::test]
+async fn sender_closed_after_receiver_dropped() {
+    let (mut sender, receiver) = oneshot::channel::<u128>();
+    mem::drop(receiver);
+    sender.closed().await;
+    assert!(sender.is_closed());
+    assert!(sender.send(5).is_err());
+}
+
+#[tokio::test]
+async fn sender_closed_then_drop_receiver_tokio() {
+    let (mut sender, receiver) = oneshot::channel::<u128>();
+    let t = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        mem::drop(receiver);
+    });
+    sender.closed().await;
+    assert!(sender.is_closed());
+    t.await.unwrap();
+}
+
+#[async_std::test]
+async fn sender_closed_then_drop_receiver_async_std() {
+    let (mut sender, receiver) = oneshot::channel::<u128>();
+    let t = async_std::task::spawn(async move {
+        async_std::task::sleep(Duration::from_millis(10)).await;
+        mem::drop(receiver);
+    });
+    sender.closed().await;
+    assert!(sender.is_closed());
+    t.await;
+}
+
+#[tokio::test]
+async fn sender_closed_in_select_then_send() {
+    let (mut sender, receiver) = oneshot::channel::<u128>();
+    tokio::select! {
+        _ = sender.closed() => panic!("Receiver is still alive"),
+        _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+    }
+    assert!(sender.send(5).is_ok());
+    assert_eq!(receiver.await, Ok(5));
+}
+
+#[tokio::test]
+async fn sender_demand_then_send() {
+    let (mut sender, receiver) = oneshot::channel::<u128>();
+    let t = tokio::spawn(async move {
+        assert!(sender.demand().await);
+        sender.send(5).unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(receiver.await, Ok(5));
+    t.await.unwrap();
+}
+
+#[tokio::test]
+async fn sender_demand_with_dropped_receiver() {
+    let (mut sender, receiver) = oneshot::channel::<u128>();
+    mem::drop(receiver);
+    assert!(!sender.demand().await);
+}
@@ -0,0 +1,106 @@
+use core::mem;
+use oneshot::TryRecvError;
+
+#[cfg(feature = "std")]
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::{maybe_loom_model, DropCounter};
+
+#[test]
+fn zero_channels() {
+    maybe_loom_model(|| {
+        let (senders, receivers) = oneshot::channels::<u128>(0);
+        assert!(senders.is_empty());
+        assert!(receivers.is_empty());
+        let ([], []) = oneshot::channels_array::<u128, 0>();
+    })
+}
+
+#[test]
+fn channels_are_independent() {
+    maybe_loom_model(|| {
+        let (senders, receivers) = oneshot::channels::<u128>(3);
+        let mut senders = senders.into_iter();
+        let mut receivers = receivers.into_iter();
+
+        assert!(senders.next().unwrap().send(0).is_ok());
+        mem::drop(senders.next());
+        let sender2 = senders.next().unwrap();
+
+        assert_eq!(receivers.next().unwrap().try_recv(), Ok(0));
+        assert_eq!(
+            receivers.next().unwrap().try_recv(),
+            Err(TryRecvError::Disconnected)
+        );
+        let receiver2 = receivers.next().unwrap();
+        assert_eq!(receiver2.try_recv(), Err(TryRecvError::Empty));
+        assert!(sender2.send(2).is_ok());
+        assert_eq!(receiver2.try_recv(), Ok(2));
+    })
+}
+
+#[test]
+fn unreceived_messages_dropped_with_last_endpoint() {
+    maybe_loom_model(|| {
+        let ([sender1, sender2], [receiver1, receiver2]) = oneshot::channels_array();
+        let (message1, counter1) = DropCounter::new(());
+        let (message2, counter2) = DropCounter::new(());
+        assert!(sender1.send(message1).is_ok());
+        assert!(sender2.send(message2).is_ok());
+
+        mem::drop(receiver2);
+        assert_eq!(counter2.count(), 1);
+        assert_eq!(counter1.count(), 0);
+        mem::drop(receiver1);
+        assert_eq!(counter1.count(), 1);
+    })
+}
+
+#[test]
+fn send_to_dropped_receiver() {
+    maybe_loom_model(|| {
+        let ([sender1, sender2], [receiver1, receiver2]) = oneshot::channels_array::<u128, 2>();
+        mem::drop((receiver1, receiver2));
+        let error = sender1.send(1).unwrap_err();
+        mem::drop(sender2);
+        // The error keeps its channel, and so the whole batch, alive.
+        assert_eq!(error.into_inner(), 1);
+    })
+}
+
+#[test]
+fn aligned_messages() {
+    #[derive(Debug, PartialEq)]
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    maybe_loom_model(|| {
+        let (senders, receivers) = oneshot::channels::<Aligned>(3);
+        for (i, sender) in senders.into_iter().enumerate() {
+            assert!(sender.send(Aligned(i as u8)).is_ok());
+        }
+        for (i, receiver) in receivers.iter().enumerate() {
+            assert_eq!(receiver.try_recv(), Ok(Aligned(i as u8)));
+        }
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn endpoints_on_other_threads() {
+    maybe_loom_model(|| {
+        let ([sender1, sender2], [receiver1, receiver2]) = oneshot::channels_array::<u128, 2>();
+        let t1 = thread::spawn(move || sender1.send(1).is_ok());
+        let t2 = thread::spawn(move || receiver2.recv());
+        assert_eq!(receiver1.recv(), Ok(1));
+        mem::drop(sender2);
+        assert!(t1.join().unwrap());
+        assert!(t2.join().unwrap().is_err());
+    })
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "std")]
+
+use core::mem;
+use core::time::Duration;
+use oneshot::{CancelToken, RecvCancelledError, RecvCancelledTimeoutError};
+
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::maybe_loom_model;
+
+#[test]
+fn message_before_cancel() {
+    maybe_loom_model(|| {
+        let token = CancelToken::new();
+        let (sender, receiver) = oneshot::channel::<u128>();
+        assert!(sender.send(1).is_ok());
+        assert_eq!(receiver.recv_cancellable(&token), Ok(1));
+        assert_eq!(
+            receiver.recv_cancellable(&token),
+            Err(RecvCancelledError::Disconnected)
+        );
+    })
+}
+
+#[test]
+fn already_cancelled_token_does_not_block() {
+    maybe_loom_model(|| {
+        let token = CancelToken::new();
+        token.cancel();
+        assert!(token.is_cancelled());
+
+        let (sender, receiver) = oneshot::channel::<u128>();
+        assert_eq!(
+            receiver.recv_cancellable(&token),
+            Err(RecvCancelledError::Cancelled)
+        );
+        // The message still wins over the cancellation, and the receiver is still usable.
+        assert!(sender.send(2).is_ok());
+        assert_eq!(receiver.recv_cancellable(&token), Ok(2));
+    })
+}
+
+#[test]
+fn disconnected() {
+    maybe_loom_model(|| {
+        let token = CancelToken::new();
+        let (sender, receiver) = oneshot::channel::<u128>();
+        mem::drop(sender);
+        assert_eq!(
+            receiver.recv_cancellable_timeout(Duration::from_millis(1), &token),
+            Err(RecvCancelledTimeoutError::Disconnected)
+        );
+    })
+}
+
+#[test]
+fn cancel_from_other_thread() {
+    maybe_loom_model(|| {
+        let token = CancelToken::new();
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = {
+            let token = token.clone();
+            thread::spawn(move || token.cancel())
+        };
+        assert_eq!(
+            receiver.recv_cancellable(&token),
+            Err(RecvCancelledError::Cancelled)
+        );
+        t.join().unwrap();
+
+        assert!(sender.send(3).is_ok());
+        assert_eq!(receiver.try_recv(), Ok(3));
+    })
+}
+
+#[test]
+fn send_or_cancel_from_other_thread() {
+    maybe_loom_model(|| {
+        let token = CancelToken::new();
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = {
+            let token = token.clone();
+            thread::spawn(move || {
+                assert!(sender.send(4).is_ok());
+                token.cancel();
+            })
+        };
+        match receiver.recv_cancellable(&token) {
+            Ok(message) => assert_eq!(message, 4),
+            Err(RecvCancelledError::Cancelled) => {
+                t.join().unwrap();
+                assert_eq!(receiver.try_recv(), Ok(4));
+                return;
+            }
+            Err(RecvCancelledError::Disconnected) => panic!("disconnected"),
+        }
+        t.join().unwrap();
+    })
+}
+
+#[cfg(not(oneshot_loom))]
+mod not_loom {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn timeout_without_cancel() {
+        let token = CancelToken::new();
+        let (_sender, receiver) = oneshot::channel::<u128>();
+        let start = Instant::now();
+        assert_eq!(
+            receiver.recv_cancellable_deadline(start + Duration::from_millis(20), &token),
+            Err(RecvCancelledTimeoutError::Timeout)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn one_token_cancels_many_receivers() {
+        let token = CancelToken::new();
+        let (senders, workers): (Vec<_>, Vec<_>) = (0..8)
+            .map(|_| {
+                let (sender, receiver) = oneshot::channel::<u128>();
+                let token = token.clone();
+                let worker = std::thread::spawn(move || {
+                    let result = receiver.recv_cancellable_timeout(Duration::from_secs(10), &token);
+                    (result, receiver)
+                });
+                (sender, worker)
+            })
+            .unzip();
+
+        std::thread::sleep(Duration::from_millis(20));
+        token.cancel();
+        for (sender, worker) in senders.into_iter().zip(workers) {
+            let (result, receiver) = worker.join().unwrap();
+            assert_eq!(result, Err(RecvCancelledTimeoutError::Cancelled));
+            assert!(sender.send(5).is_ok());
+            assert_eq!(receiver.recv(), Ok(5));
+        }
+    }
+}
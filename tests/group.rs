@@ -0,0 +1,170 @@
+#![cfg(all(feature = "std", feature = "async"))]
+
+use core::mem;
+use oneshot::{ReceiverGroup, RecvError};
+
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::maybe_loom_model;
+
+#[test]
+fn empty_group() {
+    maybe_loom_model(|| {
+        let mut group = ReceiverGroup::<u8, u128>::new();
+        assert!(group.is_empty());
+        assert_eq!(group.next_blocking(), None);
+    })
+}
+
+#[test]
+fn yields_in_completion_order() {
+    maybe_loom_model(|| {
+        let (sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (sender_b, receiver_b) = oneshot::channel::<u128>();
+        let (sender_c, receiver_c) = oneshot::channel::<u128>();
+        let mut group: ReceiverGroup<_, _> =
+            [('a', receiver_a), ('b', receiver_b), ('c', receiver_c)]
+                .into_iter()
+                .collect();
+        assert_eq!(group.len(), 3);
+
+        assert!(sender_b.send(2).is_ok());
+        assert_eq!(group.next_blocking(), Some(('b', Ok(2))));
+        mem::drop(sender_c);
+        assert_eq!(group.next_blocking(), Some(('c', Err(RecvError))));
+        assert!(sender_a.send(1).is_ok());
+        assert_eq!(group.next_blocking(), Some(('a', Ok(1))));
+        assert_eq!(group.next_blocking(), None);
+    })
+}
+
+#[test]
+fn send_on_other_thread() {
+    maybe_loom_model(|| {
+        let (sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (sender_b, receiver_b) = oneshot::channel::<u128>();
+        let mut group = ReceiverGroup::new();
+        group.insert(0, receiver_a);
+        group.insert(1, receiver_b);
+
+        let t = thread::spawn(move || {
+            assert!(sender_b.send(2).is_ok());
+            mem::drop(sender_a);
+        });
+        assert_eq!(group.next_blocking(), Some((1, Ok(2))));
+        assert_eq!(group.next_blocking(), Some((0, Err(RecvError))));
+        assert!(group.is_empty());
+        t.join().unwrap();
+    })
+}
+
+#[test]
+fn insert_after_yield_reuses_slot() {
+    maybe_loom_model(|| {
+        let mut group = ReceiverGroup::new();
+        for i in 0..3u128 {
+            let (sender, receiver) = oneshot::channel();
+            group.insert(i, receiver);
+            assert!(sender.send(i * 10).is_ok());
+            assert_eq!(group.next_blocking(), Some((i, Ok(i * 10))));
+        }
+    })
+}
+
+#[test]
+fn dropping_group_drops_receivers() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let mut group = ReceiverGroup::new();
+        group.insert((), receiver);
+        mem::drop(group);
+        assert!(sender.is_closed());
+    })
+}
+
+#[cfg(not(oneshot_loom))]
+mod not_loom {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn thousands_of_replies() {
+        let mut group = ReceiverGroup::new();
+        for i in 0..2000u64 {
+            let (sender, receiver) = oneshot::channel();
+            group.insert(i, receiver);
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(i % 20)).await;
+                sender.send(i * 2).unwrap();
+            });
+        }
+
+        let mut seen = vec![false; 2000];
+        while let Some((key, result)) = group.next_message().await {
+            assert_eq!(result, Ok(key * 2));
+            assert!(!seen[key as usize]);
+            seen[key as usize] = true;
+        }
+        assert!(seen.into_iter().all(|seen| seen));
+    }
+
+    #[tokio::test]
+    async fn wakes_task_waiting_on_empty_queue() {
+        let (sender, receiver) = oneshot::channel::<&str>();
+        let mut group = ReceiverGroup::new();
+        group.insert("key", receiver);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send("reply").unwrap();
+        });
+        assert_eq!(group.next_message().await, Some(("key", Ok("reply"))));
+        assert_eq!(group.next_message().await, None);
+    }
+
+    #[test]
+    fn send_wakes_waiting_task_once() {
+        use core::future::Future;
+        use core::pin::Pin;
+        use core::task::{Context, Poll};
+        use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+        use std::sync::Arc;
+        use std::task::Wake;
+
+        struct CountingWaker(AtomicUsize);
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let mut senders = Vec::new();
+        let mut group = ReceiverGroup::new();
+        for i in 0..100 {
+            let (sender, receiver) = oneshot::channel::<usize>();
+            group.insert(i, receiver);
+            senders.push(sender);
+        }
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = counter.clone().into();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut group.next_message())
+            .poll(&mut cx)
+            .is_pending());
+
+        senders.remove(42).send(42).unwrap();
+        assert_eq!(counter.0.load(SeqCst), 1);
+        assert_eq!(
+            Pin::new(&mut group.next_message()).poll(&mut cx),
+            Poll::Ready(Some((42, Ok(42))))
+        );
+        assert!(Pin::new(&mut group.next_message())
+            .poll(&mut cx)
+            .is_pending());
+    }
+}
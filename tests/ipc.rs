@@ -0,0 +1,115 @@
+#![cfg(all(feature = "ipc", target_os = "linux", not(oneshot_loom)))]
+
+use oneshot::ipc;
+use oneshot::{RecvError, RecvTimeoutError, TryRecvError};
+use std::mem;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn send_before_recv() {
+    let (sender, receiver) = ipc::channel::<[u64; 4]>().unwrap();
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    sender.send([1, 2, 3, 4]).unwrap();
+    assert_eq!(receiver.try_recv(), Ok([1, 2, 3, 4]));
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn drop_sender() {
+    let (sender, receiver) = ipc::channel::<u32>().unwrap();
+    mem::drop(sender);
+    assert_eq!(receiver.recv(), Err(RecvError));
+}
+
+#[test]
+fn drop_receiver() {
+    let (sender, receiver) = ipc::channel::<u32>().unwrap();
+    assert!(!sender.is_closed());
+    mem::drop(receiver);
+    assert!(sender.is_closed());
+    assert_eq!(sender.send(5).unwrap_err().into_inner(), 5);
+}
+
+#[test]
+fn recv_timeout_then_recv() {
+    let (sender, receiver) = ipc::channel::<u32>().unwrap();
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_millis(1)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    sender.send(5).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_millis(1)), Ok(5));
+}
+
+#[test]
+fn send_wakes_receiver_on_other_thread() {
+    let (sender, receiver) = ipc::channel::<u32>().unwrap();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        sender.send(7).unwrap();
+    });
+    assert_eq!(receiver.recv(), Ok(7));
+    t.join().unwrap();
+}
+
+#[test]
+fn drop_wakes_receiver_on_other_thread() {
+    let (sender, receiver) = ipc::channel::<u32>().unwrap();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        mem::drop(sender);
+    });
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(10)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+    t.join().unwrap();
+}
+
+#[test]
+fn send_from_forked_child() {
+    let (sender, receiver) = ipc::channel::<u64>().unwrap();
+    let fd = sender.into_raw_fd().unwrap();
+
+    match unsafe { libc::fork() } {
+        -1 => panic!("fork failed: {}", std::io::Error::last_os_error()),
+        0 => {
+            // Keep to plain system calls in the child, the test harness is multi threaded.
+            let sender = unsafe { ipc::Sender::<u64>::from_raw_fd(fd) }.unwrap();
+            thread::sleep(Duration::from_millis(10));
+            let sent = sender.send(0xdead_beef).is_ok();
+            unsafe { libc::_exit(if sent { 0 } else { 1 }) }
+        }
+        child => {
+            unsafe { libc::close(fd) };
+            assert_eq!(receiver.recv(), Ok(0xdead_beef));
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(child, &mut status, 0) }, child);
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        }
+    }
+}
+
+#[test]
+fn receive_in_forked_child() {
+    let (sender, receiver) = ipc::channel::<u64>().unwrap();
+    let fd = receiver.into_raw_fd().unwrap();
+
+    match unsafe { libc::fork() } {
+        -1 => panic!("fork failed: {}", std::io::Error::last_os_error()),
+        0 => {
+            let receiver = unsafe { ipc::Receiver::<u64>::from_raw_fd(fd) }.unwrap();
+            let received = receiver.recv_timeout(Duration::from_secs(10));
+            unsafe { libc::_exit(if received == Ok(3) { 0 } else { 1 }) }
+        }
+        child => {
+            unsafe { libc::close(fd) };
+            thread::sleep(Duration::from_millis(10));
+            sender.send(3).unwrap();
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(child, &mut status, 0) }, child);
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        }
+    }
+}
@@ -247,3 +247,249 @@ fn poll_then_try_recv_while_sending() {
         t.join().unwrap();
     })
 }
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_closed_then_drop_receiver() {
+    loom::model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+
+        assert_eq!(sender.poll_closed(&mut context), Poll::Pending);
+        assert_eq!(waker_handle.clone_count(), 1);
+
+        let t = thread::spawn(move || {
+            drop(receiver);
+        });
+        t.join().unwrap();
+
+        assert_eq!(waker_handle.clone_count(), 1);
+        assert_eq!(waker_handle.drop_count(), 1);
+        assert_eq!(waker_handle.wake_count(), 1);
+        assert!(sender.is_closed());
+        assert_eq!(sender.poll_closed(&mut context), Poll::Ready(()));
+    })
+}
+
+// Make sure the sender can send or be dropped while the receiver is dropped in parallel
+#[cfg(feature = "async")]
+#[test]
+fn poll_closed_then_send_during_receiver_drop() {
+    loom::model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+
+        assert_eq!(sender.poll_closed(&mut context), Poll::Pending);
+
+        let t = thread::spawn(move || {
+            drop(receiver);
+        });
+
+        let _ = sender.send(1234);
+        t.join().unwrap();
+
+        // The waker is either woken by the receiver or dropped by the sender. Never both.
+        assert_eq!(waker_handle.drop_count(), 1);
+        assert!(waker_handle.wake_count() <= 1);
+    })
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_closed_concurrently_with_receiver_drop() {
+    loom::model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+
+        let t = thread::spawn(move || {
+            drop(receiver);
+        });
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+        if sender.poll_closed(&mut context).is_pending() {
+            t.join().unwrap();
+            assert_eq!(waker_handle.wake_count(), 1);
+            assert_eq!(sender.poll_closed(&mut context), Poll::Ready(()));
+        } else {
+            t.join().unwrap();
+        }
+        assert!(sender.is_closed());
+        drop(sender);
+        assert_eq!(waker_handle.clone_count(), waker_handle.drop_count());
+    })
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_demand_concurrently_with_receiver_poll() {
+    loom::model(|| {
+        let (mut sender, mut receiver) = oneshot::channel::<u128>();
+
+        let t = thread::spawn(move || {
+            let (waker, _waker_handle) = helpers::waker::waker();
+            let mut context = task::Context::from_waker(&waker);
+            assert_eq!(Pin::new(&mut receiver).poll(&mut context), Poll::Pending);
+            receiver
+        });
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+        let pending = sender.poll_demand(&mut context).is_pending();
+        let _receiver = t.join().unwrap();
+        if pending {
+            assert_eq!(waker_handle.wake_count(), 1);
+            assert_eq!(sender.poll_demand(&mut context), Poll::Ready(true));
+        }
+        assert!(sender.is_receiver_waiting());
+        // Now wait for the close. The demand wakeup must not have left us without a waker.
+        assert_eq!(sender.poll_closed(&mut context), Poll::Pending);
+    })
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_then_close_during_send() {
+    loom::model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+
+        assert_eq!(Pin::new(&mut receiver).poll(&mut context), Poll::Pending);
+
+        let t = thread::spawn(move || sender.send(1234).is_ok());
+
+        receiver.close();
+        let sent = t.join().unwrap();
+        if sent {
+            assert_eq!(receiver.try_recv(), Ok(1234));
+        } else {
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        }
+        assert_eq!(waker_handle.clone_count(), 1);
+        assert_eq!(waker_handle.drop_count(), 1);
+    })
+}
+
+#[cfg(all(feature = "std", feature = "async"))]
+#[test]
+fn poll_then_recv_during_send() {
+    loom::model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+
+        assert_eq!(Pin::new(&mut receiver).poll(&mut context), Poll::Pending);
+
+        let t = thread::spawn(move || sender.send(1234).unwrap());
+
+        assert_eq!(receiver.recv(), Ok(1234));
+        t.join().unwrap();
+
+        // The task waker was either used by the sender or dropped when we started blocking.
+        assert_eq!(waker_handle.clone_count(), 1);
+        assert_eq!(waker_handle.drop_count(), 1);
+    })
+}
+
+#[cfg(all(feature = "std", feature = "async"))]
+#[test]
+fn poll_then_recv_ref_during_sender_drop() {
+    loom::model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+
+        assert_eq!(Pin::new(&mut receiver).poll(&mut context), Poll::Pending);
+
+        let t = thread::spawn(move || drop(sender));
+
+        assert!(receiver.recv_ref().is_err());
+        t.join().unwrap();
+
+        assert_eq!(waker_handle.clone_count(), 1);
+        assert_eq!(waker_handle.drop_count(), 1);
+    })
+}
+
+#[test]
+fn close_during_send_with() {
+    loom::model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+
+        let t = thread::spawn(move || match sender.send_with(|| 1234) {
+            Ok(()) => true,
+            Err(oneshot::SendWithError::Closed(_)) => false,
+            Err(oneshot::SendWithError::Send(error)) => {
+                assert_eq!(error.into_inner(), 1234);
+                false
+            }
+        });
+
+        receiver.close();
+        let sent = t.join().unwrap();
+        if sent {
+            assert_eq!(receiver.try_recv(), Ok(1234));
+        } else {
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        }
+    })
+}
+
+#[test]
+fn drop_receiver_during_send_with() {
+    loom::model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+
+        let t = thread::spawn(move || drop(receiver));
+
+        if let Err(oneshot::SendWithError::Send(error)) = sender.send_with(|| 1234) {
+            assert_eq!(error.into_inner(), 1234);
+        }
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn recv_during_send_with() {
+    loom::model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+
+        let t = thread::spawn(move || assert!(sender.send_with(|| 1234).is_ok()));
+
+        assert_eq!(receiver.recv(), Ok(1234));
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_then_close_during_send_with() {
+    loom::model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+
+        let (waker, waker_handle) = helpers::waker::waker();
+        let mut context = task::Context::from_waker(&waker);
+
+        assert_eq!(Pin::new(&mut receiver).poll(&mut context), Poll::Pending);
+
+        let t = thread::spawn(move || sender.send_with(|| 1234).is_ok());
+
+        receiver.close();
+        let sent = t.join().unwrap();
+        if sent {
+            assert_eq!(receiver.try_recv(), Ok(1234));
+        } else {
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        }
+        assert_eq!(waker_handle.clone_count(), 1);
+        assert_eq!(waker_handle.drop_count(), 1);
+    })
+}
@@ -0,0 +1,260 @@
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use oneshot::parker::WaitStrategy;
+use oneshot::parker::{Parker, SpinParker, TimedParker, Unparker};
+use oneshot::{RecvError, RecvTimeoutError};
+
+#[cfg(feature = "std")]
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::maybe_loom_model;
+
+/// A parker with a fake clock, advancing one tick every time it parks.
+#[derive(Default)]
+struct TickParker {
+    ticks: Cell<u64>,
+}
+
+impl Parker for TickParker {
+    type Unparker = TickUnparker;
+
+    fn unparker(&self) -> TickUnparker {
+        TickUnparker
+    }
+
+    fn park(&self) {
+        self.ticks.set(self.ticks.get() + 1);
+    }
+}
+
+impl TimedParker for TickParker {
+    type Instant = u64;
+
+    fn now(&self) -> u64 {
+        self.ticks.get()
+    }
+
+    fn park_until(&self, deadline: &u64) {
+        assert!(self.ticks.get() < *deadline);
+        self.park();
+    }
+}
+
+struct TickUnparker;
+
+impl Unparker for TickUnparker {
+    fn unpark(self) {}
+}
+
+#[test]
+fn recv_with_after_send() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel();
+        assert!(sender.send(19u128).is_ok());
+        assert_eq!(receiver.recv_with(&SpinParker), Ok(19));
+    })
+}
+
+#[test]
+fn recv_ref_with_dropped_sender() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        drop(sender);
+        assert_eq!(receiver.recv_ref_with(&SpinParker), Err(RecvError));
+    })
+}
+
+#[test]
+fn recv_deadline_with_user_clock() {
+    maybe_loom_model(|| {
+        let parker = TickParker::default();
+        let (sender, receiver) = oneshot::channel::<u128>();
+
+        assert_eq!(
+            receiver.recv_deadline_with(&parker, 3),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(parker.now(), 3);
+
+        assert!(sender.send(5).is_ok());
+        assert_eq!(receiver.recv_deadline_with(&parker, 6), Ok(5));
+        assert_eq!(parker.now(), 3);
+        assert_eq!(
+            receiver.recv_deadline_with(&parker, 6),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn recv_with_spin_parker_on_other_thread() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || receiver.recv_with(&SpinParker));
+        assert!(sender.send(5).is_ok());
+        assert_eq!(t.join().unwrap(), Ok(5));
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn spin_then_park_on_other_thread() {
+    maybe_loom_model(|| {
+        let strategy = WaitStrategy::spin_then_park(2);
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || sender.send(5).unwrap());
+        assert_eq!(receiver.recv_with(&strategy), Ok(5));
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn spin_then_park_recv_ref_sender_dropped_on_other_thread() {
+    maybe_loom_model(|| {
+        let strategy = WaitStrategy::spin_then_park(2);
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || drop(sender));
+        assert_eq!(receiver.recv_ref_with(&strategy), Err(RecvError));
+        t.join().unwrap();
+    })
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+mod wait_strategy {
+    use super::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn recv_delayed(strategy: &WaitStrategy, delay: Duration) {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            thread::sleep(delay);
+            sender.send(5).unwrap();
+        });
+        assert_eq!(receiver.recv_with(strategy), Ok(5));
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn all_strategies_receive_fast_and_slow_messages() {
+        for strategy in [
+            WaitStrategy::no_spin(),
+            WaitStrategy::spin_then_park(1000),
+            WaitStrategy::adaptive(1000),
+        ] {
+            for _ in 0..5 {
+                recv_delayed(&strategy, Duration::ZERO);
+            }
+            recv_delayed(&strategy, Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn recv_deadline_with_strategy_times_out() {
+        static STRATEGY: WaitStrategy = WaitStrategy::adaptive(100);
+
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(10);
+        assert_eq!(
+            receiver.recv_deadline_with(&STRATEGY, deadline),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(Instant::now() >= deadline);
+
+        sender.send(3).unwrap();
+        assert_eq!(receiver.recv_deadline_with(&STRATEGY, deadline), Ok(3));
+    }
+
+    #[test]
+    fn spin_receives_demand_driven_message() {
+        let strategy = WaitStrategy::spin_then_park(u32::MAX);
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            let mut sender = sender;
+            assert!(sender.wait_for_demand());
+            sender.send(9).unwrap();
+        });
+        assert_eq!(receiver.recv_with(&strategy), Ok(9));
+        t.join().unwrap();
+    }
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+mod std_parker {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::sync::Arc;
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    /// Parks with the standard library, counting the unparks. `PADDING` makes the unparker too
+    /// large to be stored inline in the channel.
+    struct CountingParker<const PADDING: usize> {
+        unparks: Arc<AtomicUsize>,
+    }
+
+    struct CountingUnparker<const PADDING: usize> {
+        thread: Thread,
+        unparks: Arc<AtomicUsize>,
+        _padding: [u8; PADDING],
+    }
+
+    impl<const PADDING: usize> Parker for CountingParker<PADDING> {
+        type Unparker = CountingUnparker<PADDING>;
+
+        fn unparker(&self) -> Self::Unparker {
+            CountingUnparker {
+                thread: thread::current(),
+                unparks: self.unparks.clone(),
+                _padding: [0; PADDING],
+            }
+        }
+
+        fn park(&self) {
+            thread::park();
+        }
+    }
+
+    impl<const PADDING: usize> Unparker for CountingUnparker<PADDING> {
+        fn unpark(self) {
+            self.unparks.fetch_add(1, SeqCst);
+            self.thread.unpark();
+        }
+    }
+
+    fn recv_with_counting_parker<const PADDING: usize>() {
+        let unparks = Arc::new(AtomicUsize::new(0));
+        let parker = CountingParker::<PADDING> {
+            unparks: unparks.clone(),
+        };
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send(5).unwrap();
+        });
+        assert_eq!(receiver.recv_with(&parker), Ok(5));
+        t.join().unwrap();
+        assert_eq!(unparks.load(SeqCst), 1);
+        // The unparker was dropped after use, only our handle is left.
+        assert_eq!(Arc::strong_count(&unparks), 2);
+    }
+
+    #[test]
+    fn recv_with_inline_unparker() {
+        recv_with_counting_parker::<0>();
+    }
+
+    #[test]
+    fn recv_with_boxed_unparker() {
+        recv_with_counting_parker::<64>();
+    }
+}
@@ -0,0 +1,90 @@
+use core::mem;
+use oneshot::{ChannelPool, TryRecvError};
+
+#[cfg(feature = "std")]
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::{maybe_loom_model, DropCounter};
+
+#[test]
+fn reuse_channels() {
+    maybe_loom_model(|| {
+        let pool = ChannelPool::new();
+        for i in 0..3u128 {
+            let (sender, receiver) = pool.channel();
+            assert!(sender.send(i).is_ok());
+            assert_eq!(receiver.try_recv(), Ok(i));
+        }
+    })
+}
+
+#[test]
+fn drop_pool_before_channels() {
+    maybe_loom_model(|| {
+        let pool = ChannelPool::new();
+        let (sender1, receiver1) = pool.channel();
+        let (sender2, receiver2) = pool.channel();
+        mem::drop((sender1, receiver1));
+        mem::drop(pool);
+
+        let (message, counter) = DropCounter::new(());
+        assert!(sender2.send(message).is_ok());
+        assert_eq!(counter.count(), 0);
+        mem::drop(receiver2);
+        assert_eq!(counter.count(), 1);
+    })
+}
+
+#[test]
+fn send_error_returns_channel_to_pool() {
+    maybe_loom_model(|| {
+        let pool = ChannelPool::new();
+        let (sender, receiver) = pool.channel();
+        mem::drop(receiver);
+        let send_error = sender.send(5u128).unwrap_err();
+        mem::drop(send_error);
+
+        let (sender, receiver) = pool.channel();
+        mem::drop(sender);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn return_channel_from_other_thread_while_creating() {
+    maybe_loom_model(|| {
+        let pool = ChannelPool::new();
+        let (sender, receiver) = pool.channel();
+
+        let t = thread::spawn(move || mem::drop(receiver));
+        mem::drop(sender);
+
+        let (sender, receiver) = pool.channel();
+        assert!(sender.send(3u128).is_ok());
+        assert_eq!(receiver.recv(), Ok(3));
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn return_channel_from_other_thread_while_dropping_pool() {
+    maybe_loom_model(|| {
+        let pool = ChannelPool::new();
+        let (sender, receiver) = pool.channel();
+
+        let t = thread::spawn(move || {
+            sender.send(1u128).unwrap();
+        });
+        mem::drop(pool);
+        assert_eq!(receiver.recv(), Ok(1));
+        t.join().unwrap();
+    })
+}
@@ -0,0 +1,165 @@
+use core::mem;
+use oneshot::TryRecvError;
+
+#[cfg(feature = "std")]
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::{maybe_loom_model, DropCounter};
+
+#[test]
+fn first_send_wins() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        assert!(sender2.send(2).is_ok());
+        assert_eq!(sender1.send(1).unwrap_err().into_inner(), 1);
+        assert_eq!(receiver.try_recv(), Ok(2));
+    })
+}
+
+#[test]
+fn disconnected_only_after_all_senders_dropped() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        mem::drop(sender1);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        mem::drop(sender2);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[test]
+fn send_after_other_sender_dropped() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        mem::drop(sender2);
+        assert!(sender1.send(1).is_ok());
+        assert_eq!(receiver.try_recv(), Ok(1));
+    })
+}
+
+#[test]
+fn send_to_dropped_receiver() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race();
+        let sender2 = sender1.clone();
+        mem::drop(receiver);
+
+        let (message1, counter1) = DropCounter::new(());
+        let (message2, counter2) = DropCounter::new(());
+        let error1 = sender1.send(message1).unwrap_err();
+        let error2 = sender2.send(message2).unwrap_err();
+        assert_eq!(counter1.count(), 0);
+        mem::drop(error1);
+        assert_eq!(counter1.count(), 1);
+        mem::drop(error2);
+        assert_eq!(counter2.count(), 1);
+    })
+}
+
+#[test]
+fn losing_message_is_not_received() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race();
+        let sender2 = sender1.clone();
+        let (message1, counter1) = DropCounter::new(1);
+        let (message2, counter2) = DropCounter::new(2);
+        assert!(sender1.send(message1).is_ok());
+        let error = sender2.send(message2).unwrap_err();
+        assert_eq!(*error.as_inner().value(), 2);
+        mem::drop(error);
+        assert_eq!(counter2.count(), 1);
+
+        assert_eq!(receiver.try_recv().unwrap().into_value(), 1);
+        assert_eq!(counter1.count(), 1);
+    })
+}
+
+#[test]
+fn losing_sender_outlives_receiver() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        assert!(sender1.send(1).is_ok());
+        assert_eq!(receiver.try_recv(), Ok(1));
+        mem::drop(receiver);
+        assert_eq!(sender2.send(2).unwrap_err().into_inner(), 2);
+    })
+}
+
+#[test]
+fn send_to_closed_receiver() {
+    maybe_loom_model(|| {
+        let (sender1, mut receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        receiver.close();
+        assert_eq!(sender1.send(1).unwrap_err().into_inner(), 1);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        mem::drop(receiver);
+        mem::drop(sender2);
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn senders_race_on_threads() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        let t = thread::spawn(move || sender2.send(2).is_ok());
+        let sent1 = sender1.send(1).is_ok();
+        let sent2 = t.join().unwrap();
+        assert!(sent1 != sent2);
+        assert_eq!(receiver.recv(), Ok(if sent1 { 1 } else { 2 }));
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn senders_dropped_on_threads() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        let t = thread::spawn(move || mem::drop(sender2));
+        mem::drop(sender1);
+        assert!(receiver.recv().is_err());
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn receiver_and_senders_dropped_on_threads() {
+    maybe_loom_model(|| {
+        let (sender1, receiver) = oneshot::race::<u128>();
+        let sender2 = sender1.clone();
+        let t1 = thread::spawn(move || sender1.send(1).is_ok());
+        let t2 = thread::spawn(move || receiver.recv());
+        mem::drop(sender2);
+        let sent = t1.join().unwrap();
+        assert_eq!(t2.join().unwrap().is_ok(), sent);
+    })
+}
+
+#[cfg(all(feature = "async", not(oneshot_loom)))]
+#[tokio::test]
+async fn first_reply_of_hedged_requests() {
+    let (sender, receiver) = oneshot::race::<&str>();
+    for (backend, delay) in [("slow", 50), ("fast", 1), ("medium", 20)] {
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            let _ = sender.send(backend);
+        });
+    }
+    mem::drop(sender);
+    assert_eq!(receiver.await, Ok("fast"));
+}
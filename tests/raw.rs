@@ -1,6 +1,6 @@
 #![cfg(not(oneshot_loom))]
 
-use oneshot::{channel, Receiver, Sender};
+use oneshot::{channel, RawSender, Receiver, Sender};
 
 #[test]
 fn test_raw_sender() {
@@ -44,3 +44,66 @@ fn test_raw_sender_and_receiver() {
         100
     )
 }
+
+#[test]
+fn test_typed_raw_sender_and_receiver() {
+    let (sender, receiver) = channel::<u32>();
+    let raw_sender = sender.into_raw_sender();
+    let raw_receiver = receiver.into_raw_receiver();
+
+    unsafe { raw_sender.into_sender() }.send(100).unwrap();
+    assert_eq!(unsafe { raw_receiver.into_receiver() }.try_recv(), Ok(100));
+}
+
+#[test]
+fn test_typed_and_untyped_pointers_match() {
+    let (sender, receiver) = channel::<u32>();
+    let raw_sender = unsafe { RawSender::<u32>::from_ptr(sender.into_raw()) };
+    let raw_receiver = receiver.into_raw_receiver();
+
+    let recreated_receiver = unsafe { Receiver::<u32>::from_raw(raw_receiver.as_ptr()) };
+    let recreated_sender = unsafe { Sender::<u32>::from_raw(raw_sender.as_ptr()) };
+    recreated_sender.send(100).unwrap();
+    assert_eq!(recreated_receiver.try_recv(), Ok(100));
+}
+
+#[cfg(any(debug_assertions, feature = "checked-raw"))]
+mod checked {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "reconstructed from a raw pointer as a Sender of `u64`")]
+    fn wrong_message_type() {
+        let (sender, _receiver) = channel::<u32>();
+        let raw = sender.into_raw();
+        let _sender = unsafe { Sender::<u64>::from_raw(raw) };
+    }
+
+    #[test]
+    #[should_panic(expected = "Was it already reconstructed?")]
+    fn sender_reconstructed_twice() {
+        let (sender, _receiver) = channel::<u32>();
+        let raw = sender.into_raw_sender();
+        let copy = raw;
+        let _sender = unsafe { raw.into_sender() };
+        let _sender = unsafe { copy.into_sender() };
+    }
+
+    #[test]
+    #[should_panic(expected = "oneshot Receiver reconstructed from a raw pointer")]
+    fn receiver_never_made_raw() {
+        let (sender, receiver) = channel::<u32>();
+        let raw = sender.into_raw();
+        let _receiver = receiver;
+        let _receiver = unsafe { Receiver::<u32>::from_raw(raw) };
+    }
+
+    #[test]
+    fn endpoint_can_go_raw_again() {
+        let (sender, receiver) = channel::<u32>();
+        let sender = unsafe { sender.into_raw_sender().into_sender() };
+        let sender = unsafe { sender.into_raw_sender().into_sender() };
+        sender.send(1).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+    }
+}
@@ -0,0 +1,122 @@
+use core::mem;
+use oneshot::scoped::ChannelSlot;
+#[cfg(not(oneshot_loom))]
+use oneshot::scoped::StaticChannel;
+use oneshot::TryRecvError;
+
+mod helpers;
+use helpers::{maybe_loom_model, DropCounter};
+
+#[test]
+fn send_and_try_recv() {
+    maybe_loom_model(|| {
+        let mut slot = ChannelSlot::new();
+        let (sender, receiver) = slot.split();
+        assert!(sender.send(19u128).is_ok());
+        assert_eq!(receiver.try_recv(), Ok(19));
+    })
+}
+
+#[test]
+fn split_again_after_endpoints_dropped() {
+    maybe_loom_model(|| {
+        let mut slot = ChannelSlot::new();
+        for i in 0..3 {
+            let (message, counter) = DropCounter::new(i);
+            let (sender, receiver) = slot.split();
+            if i % 2 == 0 {
+                assert!(sender.send(message).is_ok());
+                mem::drop(receiver);
+            } else {
+                mem::drop(receiver);
+                assert!(sender.send(message).is_err());
+            }
+            assert_eq!(counter.count(), 1);
+        }
+
+        let (sender, receiver) = slot.split();
+        mem::drop(sender);
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(TryRecvError::Disconnected)
+        ));
+    })
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+#[test]
+fn send_from_scoped_thread() {
+    let mut slot = ChannelSlot::new();
+    let (sender, receiver) = slot.split();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            sender.send(5u128).unwrap();
+        });
+        assert_eq!(receiver.recv(), Ok(5));
+    });
+}
+
+#[cfg(all(feature = "async", not(oneshot_loom)))]
+#[tokio::test]
+async fn send_from_task_and_await() {
+    let mut slot = ChannelSlot::new();
+    let (sender, receiver) = slot.split();
+    let send = async move {
+        tokio::task::yield_now().await;
+        sender.send(5u128).unwrap();
+    };
+    let (_, message) = tokio::join!(send, receiver);
+    assert_eq!(message, Ok(5));
+}
+
+#[cfg(not(oneshot_loom))]
+#[test]
+fn static_channel_splits_once_until_reset() {
+    static CHANNEL: StaticChannel<u128> = StaticChannel::new();
+
+    assert!(CHANNEL.reset());
+    let (sender, receiver) = CHANNEL.split().unwrap();
+    assert!(CHANNEL.split().is_none());
+    assert!(!CHANNEL.reset());
+
+    mem::drop(sender);
+    assert!(!CHANNEL.reset());
+    assert!(CHANNEL.split().is_none());
+    mem::drop(receiver);
+
+    assert!(CHANNEL.split().is_none());
+    assert!(CHANNEL.reset());
+    let (sender, receiver) = CHANNEL.split().unwrap();
+    assert!(sender.send(5).is_ok());
+    assert_eq!(receiver.try_recv(), Ok(5));
+}
+
+#[cfg(not(oneshot_loom))]
+#[test]
+fn static_channel_send_error_holds_channel() {
+    static CHANNEL: StaticChannel<DropCounter<()>> = StaticChannel::new();
+
+    let (message, counter) = DropCounter::new(());
+    let (sender, receiver) = CHANNEL.split().unwrap();
+    mem::drop(receiver);
+    let send_error = sender.send(message).unwrap_err();
+    assert!(!CHANNEL.reset());
+
+    mem::drop(send_error);
+    assert_eq!(counter.count(), 1);
+    assert!(CHANNEL.reset());
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+#[test]
+fn static_channel_endpoints_on_other_threads() {
+    static CHANNEL: StaticChannel<u128> = StaticChannel::new();
+
+    let (sender, receiver) = CHANNEL.split().unwrap();
+    let t = std::thread::spawn(move || receiver.recv());
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert!(sender.send(5).is_ok());
+    assert_eq!(t.join().unwrap(), Ok(5));
+    assert!(CHANNEL.reset());
+}
@@ -0,0 +1,192 @@
+#![cfg(feature = "std")]
+
+use core::mem;
+use oneshot::{Select, TryRecvError};
+
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::maybe_loom_model;
+
+#[test]
+fn returns_ready_receiver_and_leaves_others_untouched() {
+    maybe_loom_model(|| {
+        let (sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (sender_b, receiver_b) = oneshot::channel::<&str>();
+        assert!(sender_b.send("b").is_ok());
+
+        let mut select = Select::new();
+        assert_eq!(select.recv(&receiver_a), 0);
+        assert_eq!(select.recv(&receiver_b), 1);
+        assert_eq!(select.wait(), 1);
+        mem::drop(select);
+
+        assert_eq!(receiver_a.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(receiver_b.try_recv(), Ok("b"));
+        assert!(sender_a.send(1).is_ok());
+        assert_eq!(receiver_a.recv(), Ok(1));
+    })
+}
+
+#[test]
+fn first_added_ready_receiver_wins() {
+    maybe_loom_model(|| {
+        let (sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (sender_b, receiver_b) = oneshot::channel::<u128>();
+        assert!(sender_b.send(2).is_ok());
+        assert!(sender_a.send(1).is_ok());
+
+        let mut select = Select::new();
+        select.recv(&receiver_a);
+        select.recv(&receiver_b);
+        assert_eq!(select.wait(), 0);
+    })
+}
+
+#[test]
+fn disconnected_receiver_is_ready() {
+    maybe_loom_model(|| {
+        let (_sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (sender_b, receiver_b) = oneshot::channel::<u128>();
+        mem::drop(sender_b);
+
+        let mut select = Select::new();
+        select.recv(&receiver_a);
+        select.recv(&receiver_b);
+        assert_eq!(select.wait(), 1);
+        assert_eq!(receiver_b.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[test]
+fn send_on_other_thread_wakes_select() {
+    maybe_loom_model(|| {
+        let (_sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (sender_b, receiver_b) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            assert!(sender_b.send(2).is_ok());
+        });
+
+        let mut select = Select::new();
+        select.recv(&receiver_a);
+        select.recv(&receiver_b);
+        assert_eq!(select.wait(), 1);
+        assert_eq!(receiver_b.try_recv(), Ok(2));
+        assert_eq!(receiver_a.try_recv(), Err(TryRecvError::Empty));
+        t.join().unwrap();
+    })
+}
+
+#[test]
+fn drop_on_other_thread_wakes_select() {
+    maybe_loom_model(|| {
+        let (sender_a, receiver_a) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || mem::drop(sender_a));
+
+        let mut select = Select::new();
+        select.recv(&receiver_a);
+        assert_eq!(select.wait(), 0);
+        assert_eq!(receiver_a.try_recv(), Err(TryRecvError::Disconnected));
+        t.join().unwrap();
+    })
+}
+
+#[test]
+fn wait_timeout() {
+    maybe_loom_model(|| {
+        let (sender_a, receiver_a) = oneshot::channel::<u128>();
+        let (_sender_b, receiver_b) = oneshot::channel::<u128>();
+
+        let mut select = Select::new();
+        select.recv(&receiver_a);
+        select.recv(&receiver_b);
+        assert_eq!(
+            select.wait_timeout(core::time::Duration::from_millis(1)),
+            None
+        );
+
+        // The receivers are still usable after the timeout.
+        assert!(sender_a.send(1).is_ok());
+        assert_eq!(
+            select.wait_timeout(core::time::Duration::from_millis(1)),
+            Some(0)
+        );
+        mem::drop(select);
+        assert_eq!(receiver_a.recv(), Ok(1));
+    })
+}
+
+#[cfg(not(oneshot_loom))]
+mod not_loom {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn wait_deadline_reached() {
+        let (_sender, receiver) = oneshot::channel::<u128>();
+        let mut select = Select::new();
+        select.recv(&receiver);
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(20);
+        assert_eq!(select.wait_deadline(deadline), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn many_receivers_on_many_threads() {
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..10).map(|_| oneshot::channel::<usize>()).unzip();
+        let mut senders: Vec<_> = senders.into_iter().map(Some).collect();
+        let t = std::thread::spawn(move || {
+            for i in [7, 2, 9] {
+                std::thread::sleep(Duration::from_millis(5));
+                senders[i].take().unwrap().send(i).unwrap();
+            }
+            senders
+        });
+
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            // A receiver whose message was taken is disconnected, and so always ready.
+            let pending: Vec<_> = (0..receivers.len())
+                .filter(|i| !received.contains(i))
+                .collect();
+            let mut select = Select::new();
+            for &i in &pending {
+                select.recv(&receivers[i]);
+            }
+            let index = pending[select.wait()];
+            assert_eq!(receivers[index].try_recv(), Ok(index));
+            received.push(index);
+        }
+        assert_eq!(received, [7, 2, 9]);
+        t.join().unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn receiver_polled_as_future_before() {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(1), &mut receiver)
+                .await
+                .is_err()
+        );
+
+        let t = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(5));
+            sender.send(3).unwrap();
+        });
+        let mut select = Select::new();
+        select.recv(&receiver);
+        assert_eq!(select.wait(), 0);
+        assert_eq!(receiver.try_recv(), Ok(3));
+        t.join().unwrap();
+    }
+}
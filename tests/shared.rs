@@ -0,0 +1,143 @@
+#![cfg(all(feature = "std", feature = "async"))]
+
+use oneshot::{RecvError, TryRecvError};
+use std::sync::Arc;
+
+mod thread {
+    #[cfg(oneshot_loom)]
+    pub use loom::thread::spawn;
+    #[cfg(not(oneshot_loom))]
+    pub use std::thread::spawn;
+}
+
+mod helpers;
+use helpers::maybe_loom_model;
+
+#[test]
+fn try_recv_from_all_clones() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let receiver = receiver.shared();
+        let clone = receiver.clone();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(receiver.get(), None);
+
+        assert!(sender.send(5).is_ok());
+        let message = clone.try_recv().unwrap();
+        assert_eq!(*message, 5);
+        assert!(Arc::ptr_eq(&message, &receiver.try_recv().unwrap()));
+        assert_eq!(receiver.get(), Some(&5));
+        assert_eq!(receiver.recv_cloned(), Ok(5));
+    })
+}
+
+#[test]
+fn dropped_sender_disconnects_all_clones() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let receiver = receiver.shared();
+        let clone = receiver.clone();
+        drop(sender);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(clone.recv(), Err(RecvError));
+    })
+}
+
+#[test]
+fn dropping_all_clones_drops_receiver() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let receiver = receiver.shared();
+        let clone = receiver.clone();
+        drop(receiver);
+        assert!(!sender.is_closed());
+        drop(clone);
+        assert!(sender.is_closed());
+    })
+}
+
+#[test]
+fn send_wakes_all_blocked_clones() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let receiver = receiver.shared();
+        let clone = receiver.clone();
+        let t = thread::spawn(move || clone.recv().map(|message| *message));
+        assert!(sender.send(9).is_ok());
+        assert_eq!(receiver.recv().map(|message| *message), Ok(9));
+        assert_eq!(t.join().unwrap(), Ok(9));
+    })
+}
+
+#[test]
+fn sender_dropped_while_clone_blocked() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let receiver = receiver.shared();
+        let t = thread::spawn(move || receiver.recv().map(|message| *message));
+        drop(sender);
+        assert_eq!(t.join().unwrap(), Err(RecvError));
+    })
+}
+
+#[cfg(not(oneshot_loom))]
+mod not_loom {
+    use oneshot::RecvTimeoutError;
+    use std::time::Duration;
+
+    #[test]
+    fn recv_timeout_then_recv() {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let receiver = receiver.shared();
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        let t = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            sender.send(4).unwrap();
+        });
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(10)).map(|m| *m),
+            Ok(4)
+        );
+        t.join().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn many_tasks_and_threads_await_one_message() {
+        let (sender, receiver) = oneshot::channel::<String>();
+        let receiver = receiver.shared();
+
+        let tasks: Vec<_> = (0..8).map(|_| tokio::spawn(receiver.clone())).collect();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let receiver = receiver.clone();
+                std::thread::spawn(move || receiver.recv_cloned())
+            })
+            .collect();
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        sender.send("config".to_owned()).unwrap();
+
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().as_str(), "config");
+        }
+        for thread in threads {
+            assert_eq!(thread.join().unwrap().unwrap(), "config");
+        }
+        assert_eq!(receiver.await.unwrap().as_str(), "config");
+    }
+
+    #[tokio::test]
+    async fn polling_a_clone_in_select_keeps_it_usable() {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let mut receiver = receiver.shared();
+        tokio::select! {
+            _ = &mut receiver => panic!("no message sent"),
+            _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+        }
+        sender.send(3).unwrap();
+        assert_eq!(*receiver.await.unwrap(), 3);
+    }
+}
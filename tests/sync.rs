@@ -1,5 +1,5 @@
 use core::mem;
-use oneshot::TryRecvError;
+use oneshot::{ChannelState, TryRecvError};
 
 #[cfg(feature = "std")]
 use oneshot::{RecvError, RecvTimeoutError};
@@ -367,3 +367,514 @@ fn dropping_sender_disconnects_receiver() {
         assert!(receiver.is_closed());
     });
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_closed_after_receiver_dropped() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+        mem::drop(receiver);
+        sender.wait_closed();
+        assert!(sender.is_closed());
+        assert!(sender.wait_closed_timeout(Duration::from_millis(0)));
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_closed_before_drop_receiver() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(2));
+            mem::drop(receiver);
+        });
+        sender.wait_closed();
+        assert!(sender.is_closed());
+        assert!(sender.send(5).is_err());
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_closed_timeout_before_drop_receiver() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(2));
+            mem::drop(receiver);
+        });
+        assert!(sender.wait_closed_timeout(Duration::from_secs(1)));
+        assert!(sender.is_closed());
+        t.join().unwrap();
+    })
+}
+
+#[cfg(all(feature = "std", not(all(oneshot_test_delay, oneshot_loom))))]
+#[test]
+fn wait_closed_deadline_time_should_elapse() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+
+        let start = Instant::now();
+        #[cfg(not(oneshot_loom))]
+        let timeout = Duration::from_millis(100);
+        #[cfg(oneshot_loom)]
+        let timeout = Duration::from_millis(1);
+
+        assert!(!sender.wait_closed_deadline(start + timeout));
+        assert!(start.elapsed() > timeout);
+        assert!(start.elapsed() < timeout * 3);
+
+        // The sender is still usable after the timeout
+        assert!(sender.send(5).is_ok());
+        assert_eq!(receiver.try_recv(), Ok(5));
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_for_demand_then_send() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+        assert!(!sender.is_receiver_waiting());
+        let t = thread::spawn(move || {
+            assert!(sender.wait_for_demand());
+            assert!(sender.is_receiver_waiting());
+            sender.send(9).unwrap();
+        });
+        assert_eq!(receiver.recv(), Ok(9));
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_for_demand_with_dropped_receiver() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(2));
+            mem::drop(receiver);
+        });
+        assert!(!sender.wait_for_demand());
+        assert!(sender.is_closed());
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn demand_does_not_disturb_wait_closed() {
+    maybe_loom_model(|| {
+        let (mut sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            assert_eq!(
+                receiver.recv_timeout(Duration::from_millis(1)),
+                Err(RecvTimeoutError::Timeout)
+            );
+            mem::drop(receiver);
+        });
+        sender.wait_closed();
+        assert!(sender.is_receiver_waiting());
+        t.join().unwrap();
+    })
+}
+
+#[test]
+fn try_recv_does_not_signal_demand() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        #[cfg(any(feature = "std", feature = "async"))]
+        assert!(!sender.is_receiver_waiting());
+        mem::drop(sender);
+    })
+}
+
+#[test]
+fn close_before_send() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        receiver.close();
+        assert!(sender.is_closed());
+        assert!(receiver.is_closed());
+        let send_error = sender.send(5).unwrap_err();
+        assert_eq!(*send_error.as_inner(), 5);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(send_error.into_inner(), 5);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[test]
+fn close_after_send_keeps_message() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        sender.send(5).unwrap();
+        receiver.close();
+        assert!(receiver.has_message());
+        assert_eq!(receiver.try_recv(), Ok(5));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[test]
+fn close_then_drop_sender() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        receiver.close();
+        mem::drop(sender);
+        assert!(receiver.is_closed());
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[test]
+fn close_then_drop_receiver_before_send_error() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel();
+        let (message, counter) = DropCounter::new(());
+        receiver.close();
+        let send_error = sender.send(message).unwrap_err();
+        mem::drop(receiver);
+        assert_eq!(counter.count(), 0);
+        mem::drop(send_error);
+        assert_eq!(counter.count(), 1);
+    })
+}
+
+#[test]
+fn close_then_drop_send_error_before_receiver() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel();
+        let (message, counter) = DropCounter::new(());
+        receiver.close();
+        let send_error = sender.send(message).unwrap_err();
+        mem::drop(send_error);
+        assert_eq!(counter.count(), 1);
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(TryRecvError::Disconnected)
+        ));
+        mem::drop(receiver);
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn close_then_recv() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        receiver.close();
+        assert_eq!(receiver.recv_ref(), Err(RecvError));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(1)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        assert_eq!(receiver.recv(), Err(RecvError));
+        assert!(sender.send(5).is_err());
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn close_while_sending() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || sender.send(5).map_err(|e| e.into_inner()));
+        receiver.close();
+        match t.join().unwrap() {
+            Ok(()) => assert_eq!(receiver.try_recv(), Ok(5)),
+            Err(message) => {
+                assert_eq!(message, 5);
+                assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+            }
+        }
+    })
+}
+
+#[test]
+fn send_with_calls_closure_once() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let mut calls = 0;
+        assert!(sender
+            .send_with(|| {
+                calls += 1;
+                7
+            })
+            .is_ok());
+        assert_eq!(calls, 1);
+        assert_eq!(receiver.try_recv(), Ok(7));
+    })
+}
+
+#[test]
+fn send_with_skips_closure_on_dropped_receiver() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        mem::drop(receiver);
+        match sender.send_with(|| -> u128 { panic!("closure called") }) {
+            Err(oneshot::SendWithError::Closed(_)) => (),
+            _ => panic!("expected Closed"),
+        }
+    })
+}
+
+#[test]
+fn send_with_skips_closure_on_closed_receiver() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        receiver.close();
+        let (message, counter) = DropCounter::new(());
+        match sender.send_with(move || {
+            mem::drop(message);
+            1
+        }) {
+            Err(oneshot::SendWithError::Closed(f)) => {
+                assert_eq!(counter.count(), 0);
+                mem::drop(f);
+                assert_eq!(counter.count(), 1);
+            }
+            _ => panic!("expected Closed"),
+        }
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[test]
+fn send_with_receiver_dropped_during_closure() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let mut receiver = Some(receiver);
+        match sender.send_with(|| {
+            mem::drop(receiver.take());
+            9
+        }) {
+            Err(oneshot::SendWithError::Send(error)) => assert_eq!(error.into_inner(), 9),
+            _ => panic!("expected Send"),
+        }
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn send_with_while_receiver_dropped_on_other_thread() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || mem::drop(receiver));
+        match sender.send_with(|| 3) {
+            Ok(()) | Err(oneshot::SendWithError::Closed(_)) => (),
+            Err(oneshot::SendWithError::Send(error)) => assert_eq!(error.into_inner(), 3),
+        }
+        t.join().unwrap();
+    })
+}
+
+#[test]
+fn send_with_receiver_closed_during_closure() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        match sender.send_with(|| {
+            assert_eq!(receiver.state(), ChannelState::SenderWriting);
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+            receiver.close();
+            assert_eq!(receiver.state(), ChannelState::Closed);
+            9
+        }) {
+            Err(oneshot::SendWithError::Send(error)) => assert_eq!(error.into_inner(), 9),
+            _ => panic!("expected Send"),
+        }
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    })
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+#[test]
+fn send_with_panicking_closure() {
+    let (sender, receiver) = oneshot::channel::<u128>();
+    let t = thread::spawn(move || receiver.recv());
+    thread::sleep(Duration::from_millis(10));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+        sender.send_with(|| -> u128 { panic!("no message") })
+    }));
+    assert!(result.is_err());
+    assert_eq!(t.join().unwrap(), Err(RecvError));
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+#[test]
+fn recv_timeout_during_send_with() {
+    let (sender, receiver) = oneshot::channel::<u128>();
+    let t = thread::spawn(move || {
+        sender
+            .send_with(|| {
+                thread::sleep(Duration::from_millis(20));
+                3
+            })
+            .is_ok()
+    });
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_millis(1)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert_eq!(receiver.state(), ChannelState::SenderWriting);
+    assert_eq!(receiver.recv_ref(), Ok(3));
+    assert!(t.join().unwrap());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_closed_then_close_receiver() {
+    maybe_loom_model(|| {
+        let (mut sender, mut receiver) = oneshot::channel::<u128>();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(2));
+            receiver.close();
+            receiver
+        });
+        sender.wait_closed();
+        assert!(sender.is_closed());
+        assert!(sender.send(5).is_err());
+        t.join().unwrap();
+    })
+}
+
+#[test]
+fn try_peek_keeps_message() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel();
+        let (message, counter) = DropCounter::new(19u128);
+        assert!(matches!(receiver.try_peek(), Err(TryRecvError::Empty)));
+        sender.send(message).unwrap();
+
+        assert_eq!(*receiver.try_peek().unwrap().value(), 19);
+        assert_eq!(*receiver.try_peek().unwrap().value(), 19);
+        assert!(receiver.has_message());
+        assert_eq!(counter.count(), 0);
+
+        let message = receiver.try_recv().unwrap();
+        assert_eq!(*message.value(), 19);
+        assert!(matches!(
+            receiver.try_peek(),
+            Err(TryRecvError::Disconnected)
+        ));
+        mem::drop(message);
+        assert_eq!(counter.count(), 1);
+    })
+}
+
+#[test]
+fn try_peek_then_drop_receiver() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel();
+        let (message, counter) = DropCounter::new(());
+        sender.send(message).unwrap();
+        assert!(receiver.try_peek().is_ok());
+        mem::drop(receiver);
+        assert_eq!(counter.count(), 1);
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn peek_timeout_before_send() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(2));
+            sender.send(9u128).unwrap();
+        });
+        assert_eq!(receiver.peek_timeout(Duration::from_secs(1)), Ok(&9));
+        assert_eq!(receiver.try_peek(), Ok(&9));
+        assert_eq!(receiver.recv(), Ok(9));
+        t.join().unwrap();
+    })
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn peek_timeout_with_dropped_sender() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        assert_eq!(
+            receiver.peek_deadline(Instant::now()),
+            Err(RecvTimeoutError::Timeout)
+        );
+        mem::drop(sender);
+        assert_eq!(
+            receiver.peek_timeout(Duration::from_secs(1)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    })
+}
+
+#[test]
+fn state_through_send_and_receive() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        assert_eq!(sender.state(), ChannelState::Empty);
+        assert_eq!(receiver.state(), ChannelState::Empty);
+        assert!(sender.send(5).is_ok());
+        assert_eq!(receiver.state(), ChannelState::Message);
+        assert_eq!(receiver.try_recv(), Ok(5));
+        assert_eq!(receiver.state(), ChannelState::Disconnected);
+    })
+}
+
+#[test]
+fn state_of_dropped_and_closed_channels() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        mem::drop(receiver);
+        assert_eq!(sender.state(), ChannelState::Disconnected);
+
+        let (sender, receiver) = oneshot::channel::<u128>();
+        mem::drop(sender);
+        assert_eq!(receiver.state(), ChannelState::Disconnected);
+
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        receiver.close();
+        assert_eq!(sender.state(), ChannelState::Closed);
+        mem::drop(sender);
+        assert_eq!(receiver.state(), ChannelState::Closed);
+    })
+}
+
+#[test]
+fn state_from_closed_to_disconnected() {
+    maybe_loom_model(|| {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        receiver.close();
+        let error = sender.send(5).unwrap_err();
+        assert_eq!(receiver.state(), ChannelState::Closed);
+        mem::drop(error);
+        assert_eq!(receiver.state(), ChannelState::Disconnected);
+    })
+}
+
+#[cfg(all(feature = "std", not(oneshot_loom)))]
+#[test]
+fn state_while_receiver_blocks() {
+    let (sender, receiver) = oneshot::channel::<u128>();
+    let t = thread::spawn(move || receiver.recv());
+    while sender.state() == ChannelState::Empty {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(sender.state(), ChannelState::ReceiverWaitingSync);
+    assert!(sender.send(5).is_ok());
+    assert_eq!(t.join().unwrap(), Ok(5));
+}
+
+#[test]
+fn debug_shows_state() {
+    maybe_loom_model(|| {
+        let (sender, receiver) = oneshot::channel::<u128>();
+        assert!(format!("{:?}", sender).contains("state: Empty"));
+        assert!(sender.send(5).is_ok());
+        assert!(format!("{:?}", receiver).contains("state: Message"));
+    })
+}
@@ -0,0 +1,176 @@
+#![cfg(all(feature = "async", not(oneshot_loom)))]
+
+use core::future::{self, Future};
+use core::pin::Pin;
+use core::time::Duration;
+use oneshot::timer::Timer;
+use oneshot::RecvTimeoutError;
+
+/// A timer whose sleeps are either already done or never complete.
+struct FixedTimer;
+
+impl Timer for FixedTimer {
+    type Instant = bool;
+    type Sleep = Pin<Box<dyn Future<Output = ()>>>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        self.sleep_until(duration.is_zero())
+    }
+
+    fn sleep_until(&self, expired: bool) -> Self::Sleep {
+        if expired {
+            Box::pin(future::ready(()))
+        } else {
+            Box::pin(future::pending())
+        }
+    }
+}
+
+#[tokio::test]
+async fn custom_timer_expired_keeps_receiver_usable() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    assert_eq!(
+        receiver.timeout_with(&FixedTimer, Duration::ZERO).await,
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert_eq!(
+        receiver.deadline_with(&FixedTimer, true).await,
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert!(sender.send(5).is_ok());
+    assert_eq!(receiver.await, Ok(5));
+}
+
+#[tokio::test]
+async fn custom_timer_message_before_timeout() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        sender.send(3).unwrap();
+    });
+    assert_eq!(receiver.deadline_with(&FixedTimer, false).await, Ok(3));
+    assert_eq!(
+        receiver.deadline_with(&FixedTimer, false).await,
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[tokio::test]
+async fn message_sent_before_expired_timeout_is_received() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    assert!(sender.send(7).is_ok());
+    assert_eq!(receiver.deadline_with(&FixedTimer, true).await, Ok(7));
+}
+
+#[tokio::test]
+async fn sender_dropped_during_timeout() {
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(sender);
+    });
+    assert_eq!(
+        receiver.deadline_with(&FixedTimer, false).await,
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[cfg(feature = "timer-thread")]
+mod thread_timer {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn timeout_then_receive() {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        let start = Instant::now();
+        assert_eq!(
+            receiver.timeout(Duration::from_millis(20)).await,
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send(1).unwrap();
+        });
+        assert_eq!(receiver.timeout(Duration::from_secs(10)).await, Ok(1));
+    }
+
+    #[async_std::test]
+    async fn deadline_in_the_past() {
+        let (_sender, mut receiver) = oneshot::channel::<u128>();
+        assert_eq!(
+            receiver.deadline(Instant::now()).await,
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn many_timeouts_fire_in_order() {
+        let mut tasks = Vec::new();
+        for millis in [50, 10, 30, 20, 40] {
+            tasks.push(tokio::spawn(async move {
+                let (_sender, mut receiver) = oneshot::channel::<u128>();
+                let start = Instant::now();
+                let result = receiver.timeout(Duration::from_millis(millis)).await;
+                (result, start.elapsed(), Duration::from_millis(millis))
+            }));
+        }
+        for task in tasks {
+            let (result, elapsed, timeout) = task.await.unwrap();
+            assert_eq!(result, Err(RecvTimeoutError::Timeout));
+            assert!(elapsed >= timeout);
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_timeout_does_not_fire() {
+        let (sender, mut receiver) = oneshot::channel::<u128>();
+        {
+            let timeout = receiver.timeout(Duration::from_millis(10));
+            let _ = tokio::time::timeout(Duration::from_millis(1), timeout).await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(sender.send(2).is_ok());
+        assert_eq!(receiver.await, Ok(2));
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn tokio_timer() {
+    use oneshot::timer::TokioTimer;
+
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    assert_eq!(
+        receiver
+            .timeout_with(&TokioTimer, Duration::from_millis(10))
+            .await,
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert!(sender.send(4).is_ok());
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    assert_eq!(receiver.deadline_with(&TokioTimer, deadline).await, Ok(4));
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn async_std_timer() {
+    use oneshot::timer::AsyncStdTimer;
+
+    let (sender, mut receiver) = oneshot::channel::<u128>();
+    assert_eq!(
+        receiver
+            .timeout_with(&AsyncStdTimer, Duration::from_millis(10))
+            .await,
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert!(sender.send(4).is_ok());
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    assert_eq!(
+        receiver.deadline_with(&AsyncStdTimer, deadline).await,
+        Ok(4)
+    );
+}
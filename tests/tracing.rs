@@ -0,0 +1,288 @@
+#![cfg(all(feature = "tracing", feature = "std", not(oneshot_loom)))]
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+/// An event emitted by oneshot, with its fields formatted.
+#[derive(Debug, Default)]
+struct Recorded {
+    level: Option<Level>,
+    parent: Option<u64>,
+    channel: String,
+    message: String,
+    outcome: Option<String>,
+    error: Option<String>,
+}
+
+impl Visit for Recorded {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{:?}", value);
+        match field.name() {
+            "channel" => self.channel = value,
+            "message" => self.message = value,
+            "outcome" => self.outcome = Some(value.trim_matches('"').to_owned()),
+            "error" => self.error = Some(value),
+            _ => (),
+        }
+    }
+}
+
+/// Records the events of the `oneshot` target, and keeps track of the entered spans.
+#[derive(Clone, Default)]
+struct Recorder {
+    events: Arc<Mutex<Vec<Recorded>>>,
+    /// Every span created, with the stack of entered ones.
+    spans: Arc<Mutex<(Vec<&'static Metadata<'static>>, Vec<Id>)>>,
+}
+
+impl Recorder {
+    fn record(test: impl FnOnce()) -> Vec<Recorded> {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), test);
+        let events = std::mem::take(&mut *recorder.events.lock().unwrap());
+        events
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.0.push(span.metadata());
+        Id::from_u64(spans.0.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        if event.metadata().target() != "oneshot" {
+            return;
+        }
+        let mut recorded = Recorded {
+            level: Some(*event.metadata().level()),
+            parent: event.parent().map(Id::into_u64),
+            ..Recorded::default()
+        };
+        event.record(&mut recorded);
+        self.events.lock().unwrap().push(recorded);
+    }
+
+    fn enter(&self, span: &Id) {
+        self.spans.lock().unwrap().1.push(span.clone());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.spans.lock().unwrap().1.pop();
+    }
+
+    fn current_span(&self) -> Current {
+        let spans = self.spans.lock().unwrap();
+        match spans.1.last() {
+            Some(id) => Current::new(id.clone(), spans.0[id.into_u64() as usize - 1]),
+            None => Current::none(),
+        }
+    }
+}
+
+fn messages(events: &[Recorded]) -> Vec<&str> {
+    events.iter().map(|event| event.message.as_str()).collect()
+}
+
+#[test]
+fn send_then_recv() {
+    let events = Recorder::record(|| {
+        let (sender, receiver) = oneshot::channel();
+        sender.send(5u32).unwrap();
+        assert_eq!(receiver.recv(), Ok(5));
+    });
+    assert_eq!(
+        messages(&events),
+        ["channel created", "message sent", "message received"]
+    );
+    assert_eq!(events[1].outcome.as_deref(), Some("stored"));
+    assert!(events
+        .iter()
+        .all(|event| event.channel == events[0].channel));
+    assert!(events.iter().all(|event| event.parent.is_none()));
+}
+
+#[test]
+fn send_wakes_receiver() {
+    let (sender, receiver) = oneshot::channel();
+    let receiver = thread::spawn(move || receiver.recv());
+    thread::sleep(Duration::from_millis(50));
+    let events = Recorder::record(|| sender.send(5u32).unwrap());
+    assert_eq!(receiver.join().unwrap(), Ok(5));
+    assert_eq!(messages(&events), ["message sent"]);
+    assert_eq!(events[0].outcome.as_deref(), Some("woke receiver"));
+}
+
+#[test]
+fn send_to_dropped_receiver() {
+    let events = Recorder::record(|| {
+        let (sender, receiver) = oneshot::channel();
+        drop(receiver);
+        assert!(sender.send(5u32).is_err());
+    });
+    assert_eq!(
+        messages(&events),
+        ["channel created", "receiver dropped", "message sent"]
+    );
+    assert_eq!(events[2].outcome.as_deref(), Some("receiver gone"));
+    assert_eq!(events[2].level, Some(Level::DEBUG));
+}
+
+#[test]
+fn message_dropped_with_receiver() {
+    let events = Recorder::record(|| {
+        let (sender, receiver) = oneshot::channel();
+        sender.send(5u32).unwrap();
+        drop(receiver);
+    });
+    assert_eq!(
+        messages(&events),
+        [
+            "channel created",
+            "message sent",
+            "receiver dropped",
+            "unreceived message dropped"
+        ]
+    );
+}
+
+#[test]
+fn sender_dropped() {
+    let events = Recorder::record(|| {
+        let (sender, receiver) = oneshot::channel::<u32>();
+        drop(sender);
+        assert!(receiver.recv().is_err());
+    });
+    assert_eq!(
+        messages(&events),
+        [
+            "channel created",
+            "sender dropped without sending",
+            "receive failed"
+        ]
+    );
+    assert_eq!(events[2].error.as_deref(), Some("RecvError"));
+}
+
+#[test]
+fn recv_timeout() {
+    let events = Recorder::record(|| {
+        let (_sender, receiver) = oneshot::channel::<u32>();
+        assert!(receiver.recv_timeout(Duration::from_millis(1)).is_err());
+    });
+    assert_eq!(
+        messages(&events),
+        [
+            "channel created",
+            "receive failed",
+            "receiver dropped",
+            "sender dropped without sending"
+        ]
+    );
+    assert_eq!(events[1].error.as_deref(), Some("Timeout"));
+}
+
+#[test]
+fn events_are_children_of_the_creating_span() {
+    let events = Recorder::record(|| {
+        let (sender, receiver) = {
+            let span = tracing::info_span!("request");
+            let _entered = span.enter();
+            oneshot::channel()
+        };
+        sender.send(5u32).unwrap();
+        assert_eq!(receiver.recv(), Ok(5));
+    });
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|event| event.parent == Some(1)));
+}
+
+#[test]
+fn channels_have_distinct_ids() {
+    let events = Recorder::record(|| {
+        let _first = oneshot::channel::<u32>();
+        let _second = oneshot::channel::<u32>();
+    });
+    assert_ne!(events[0].channel, events[1].channel);
+}
+
+#[test]
+fn peek_does_not_receive() {
+    let events = Recorder::record(|| {
+        let (sender, mut receiver) = oneshot::channel();
+        sender.send(5u32).unwrap();
+        assert_eq!(receiver.peek_timeout(Duration::from_millis(1)), Ok(&5));
+        assert_eq!(receiver.recv(), Ok(5));
+    });
+    assert_eq!(
+        messages(&events),
+        ["channel created", "message sent", "message received"]
+    );
+}
+
+#[test]
+fn race_loser_has_no_channel() {
+    let events = Recorder::record(|| {
+        let (sender1, receiver) = oneshot::race();
+        let sender2 = sender1.clone();
+        sender1.send(1u32).unwrap();
+        assert!(sender2.send(2).is_err());
+        assert_eq!(receiver.recv(), Ok(1));
+    });
+    assert_eq!(
+        messages(&events),
+        ["channel created", "message sent", "message received"]
+    );
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn await_message() {
+    let events = Recorder::record(|| {
+        let (sender, receiver) = oneshot::channel();
+        sender.send(5u32).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        assert_eq!(runtime.block_on(receiver), Ok(5));
+    });
+    assert_eq!(
+        messages(&events),
+        [
+            "channel created",
+            "message sent",
+            "message received",
+            "receiver dropped"
+        ]
+    );
+}
+
+#[cfg(feature = "timer-thread")]
+#[test]
+fn async_timeout() {
+    let events = Recorder::record(|| {
+        let (_sender, mut receiver) = oneshot::channel::<u32>();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let timeout = receiver.timeout(Duration::from_millis(1));
+        assert!(runtime.block_on(timeout).is_err());
+    });
+    assert_eq!(messages(&events)[1], "receive failed");
+    assert_eq!(events[1].error.as_deref(), Some("Timeout"));
+}